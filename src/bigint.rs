@@ -0,0 +1,507 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// The largest power of ten that fits in a limb, used to move between limbs
+/// and decimal digits nine digits at a time
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+/// A signed integer of any size, stored as little-endian base 2^32 limbs.
+/// Trailing zero limbs are always trimmed and zero is never negative, so each
+/// value has exactly one representation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        Self {
+            negative,
+            magnitude,
+        }
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub(crate) fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub(crate) fn is_odd(&self) -> bool {
+        self.magnitude.first().is_some_and(|limb| limb & 1 == 1)
+    }
+
+    /// The number of bits needed for the magnitude, which is 0 for zero
+    pub(crate) fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() as u64 * 32 - u64::from(top.leading_zeros()),
+            None => 0,
+        }
+    }
+
+    /// Parses decimal digits with an optional leading `-`
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return None;
+        }
+
+        // The leading chunk takes whatever is left over so the rest are full
+        let mut magnitude = vec![];
+        let mut start = 0;
+        let mut end = match digits.len() % DECIMAL_DIGITS {
+            0 => DECIMAL_DIGITS,
+            head => head,
+        };
+        while start < digits.len() {
+            let chunk = &digits[start..end];
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_small_add(&mut magnitude, scale, chunk.parse().ok()?);
+            start = end;
+            end += DECIMAL_DIGITS;
+        }
+        Some(Self::new(negative, magnitude))
+    }
+
+    /// The exact value of a whole, finite float
+    pub(crate) fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+        if value == 0.0 {
+            return Some(BigInt::from(0));
+        }
+
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        let mantissa = if exponent == 0 {
+            fraction << 1
+        } else {
+            fraction | (1 << 52)
+        };
+
+        // value = mantissa * 2^shift, and being whole any bits a negative
+        // shift drops are zero
+        let shift = exponent - 1075;
+        let magnitude = if shift < 0 {
+            split(mantissa >> -shift)
+        } else {
+            shift_left(&split(mantissa), shift as usize)
+        };
+        Some(Self::new(value < 0.0, magnitude))
+    }
+
+    pub(crate) fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |acc, limb| acc << 32 | *limb as u64);
+
+        if !self.negative {
+            i64::try_from(value).ok()
+        } else if value <= i64::MIN.unsigned_abs() {
+            Some((value as i64).wrapping_neg())
+        } else {
+            None
+        }
+    }
+
+    /// The nearest float, or an infinity for values beyond its range
+    pub(crate) fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Divides rounding the quotient down, so the remainder takes the sign of
+    /// the divisor. `None` when dividing by zero
+    pub(crate) fn div_mod_floor(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem(&self.magnitude, &divisor.magnitude);
+        let quotient = BigInt::new(self.negative != divisor.negative, quotient);
+        let remainder = BigInt::new(self.negative, remainder);
+
+        if !remainder.is_zero() && remainder.negative != divisor.negative {
+            Some((&quotient - &BigInt::from(1), &remainder + divisor))
+        } else {
+            Some((quotient, remainder))
+        }
+    }
+
+    pub(crate) fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        BigInt::new(value < 0, split(value.unsigned_abs()))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare(&self.magnitude, &other.magnitude),
+            (true, true) => compare(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add(&self.magnitude, &other.magnitude));
+        }
+
+        // Opposite signs, so the larger magnitude decides the sign
+        match compare(&self.magnitude, &other.magnitude) {
+            Ordering::Less => {
+                BigInt::new(other.negative, subtract(&other.magnitude, &self.magnitude))
+            }
+            _ => BigInt::new(self.negative, subtract(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            multiply(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off nine decimal digits at a time, least significant first
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, DECIMAL_BASE);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(head) = chunks.next() {
+            write!(f, "{}", head)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn split(value: u64) -> Vec<u32> {
+    let mut magnitude = vec![value as u32, (value >> 32) as u32];
+    trim(&mut magnitude);
+    magnitude
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, limb) in long.iter().enumerate() {
+        let sum = *limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// Subtracts `b` from `a`, which must be at least as large
+fn subtract(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter().enumerate() {
+        let mut difference = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = if difference < 0 {
+            difference += 1 << 32;
+            1
+        } else {
+            0
+        };
+        result.push(difference as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+fn multiply(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let product = *x as u64 * *y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+/// Multiplies in place by a single limb and adds another, as when reading
+/// digits
+fn mul_small_add(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in magnitude.iter_mut() {
+        let product = *limb as u64 * factor as u64 + carry;
+        *limb = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+fn div_rem_small(magnitude: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; magnitude.len()];
+    let mut remainder = 0u64;
+    for (i, limb) in magnitude.iter().enumerate().rev() {
+        let current = remainder << 32 | *limb as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+/// Truncating division of magnitudes. Divisors wider than a limb fall back to
+/// binary long division, which is plenty for the sizes scripts work with
+fn div_rem(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = divisor {
+        let (quotient, remainder) = div_rem_small(dividend, *divisor);
+        return (quotient, split(remainder as u64));
+    }
+    if compare(dividend, divisor) == Ordering::Less {
+        return (vec![], dividend.to_vec());
+    }
+
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder: Vec<u32> = vec![];
+    for bit in (0..dividend.len() * 32).rev() {
+        remainder = shift_left(&remainder, 1);
+        if dividend[bit / 32] >> (bit % 32) & 1 == 1 {
+            match remainder.first_mut() {
+                Some(limb) => *limb |= 1,
+                None => remainder.push(1),
+            }
+        }
+        if compare(&remainder, divisor) != Ordering::Less {
+            remainder = subtract(&remainder, divisor);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+fn shift_left(magnitude: &[u32], bits: usize) -> Vec<u32> {
+    let (limbs, bits) = (bits / 32, bits % 32);
+    let mut result = vec![0u32; limbs];
+    let mut carry = 0u32;
+    for limb in magnitude {
+        if bits == 0 {
+            result.push(*limb);
+        } else {
+            result.push(limb << bits | carry);
+            carry = limb >> (32 - bits);
+        }
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    trim(&mut result);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn big(value: &str) -> BigInt {
+        BigInt::parse(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for value in [
+            "0",
+            "7",
+            "-42",
+            "4294967296",
+            "1000000000",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(big(value).to_string(), value);
+        }
+        assert_eq!(big("-0"), BigInt::from(0));
+        assert_eq!(big("000123").to_string(), "123");
+        assert!(BigInt::parse("12a").is_none());
+        assert!(BigInt::parse("-").is_none());
+    }
+
+    #[test]
+    fn test_i64_conversion() {
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(BigInt::from(i64::MAX).to_i64(), Some(i64::MAX));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+    }
+
+    #[test]
+    fn test_f64_conversion() {
+        assert_eq!(
+            BigInt::from_f64(1e20).unwrap().to_string(),
+            "100000000000000000000"
+        );
+        assert_eq!(BigInt::from_f64(-3.0), Some(BigInt::from(-3)));
+        assert_eq!(BigInt::from_f64(-0.0), Some(BigInt::from(0)));
+        assert!(BigInt::from_f64(0.5).is_none());
+        assert!(BigInt::from_f64(f64::INFINITY).is_none());
+        assert_eq!(big("100000000000000000000").to_f64(), 1e20);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+        assert_eq!((&a + &b).to_string(), "-864197532086419753208641975320");
+        assert_eq!((&a - &b).to_string(), "1111111110111111111011111111100");
+        assert_eq!(
+            (&a * &b).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+        assert_eq!((&a - &a), BigInt::from(0));
+        assert_eq!(
+            BigInt::from(2).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert!(b < a);
+        assert!(big("-5") > big("-6"));
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(BigInt::from(0).bits(), 0);
+        assert_eq!(BigInt::from(-1).bits(), 1);
+        assert_eq!(BigInt::from(255).bits(), 8);
+        assert_eq!(BigInt::from(2).pow(100).bits(), 101);
+        assert!(BigInt::from(-3).is_odd());
+        assert!(!BigInt::from(2).pow(100).is_odd());
+        assert!(!BigInt::from(0).is_odd());
+    }
+
+    #[test]
+    fn test_div_mod_floor() {
+        let a = big("1267650600228229401496703205376");
+        let b = big("4294967297");
+        let (quotient, remainder) = a.div_mod_floor(&b).unwrap();
+        assert_eq!(&(&quotient * &b) + &remainder, a);
+        assert!(remainder < b && !remainder.is_negative());
+
+        let (quotient, remainder) = big("-7").div_mod_floor(&big("2")).unwrap();
+        assert_eq!((quotient.to_i64(), remainder.to_i64()), (Some(-4), Some(1)));
+        let (quotient, remainder) = big("7").div_mod_floor(&big("-2")).unwrap();
+        assert_eq!(
+            (quotient.to_i64(), remainder.to_i64()),
+            (Some(-4), Some(-1))
+        );
+        assert!(a.div_mod_floor(&BigInt::from(0)).is_none());
+    }
+}
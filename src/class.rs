@@ -0,0 +1,278 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    rc::Rc,
+};
+
+use crate::{function::Function, interpreter, loxerror::LoxError, object::Object};
+
+#[derive(Debug)]
+pub(crate) struct Class {
+    pub(crate) name: String,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<String, Rc<Function>>,
+    getters: HashMap<String, Rc<Function>>,
+    class_methods: HashMap<String, Rc<Function>>,
+    /// Fields set on the class object itself, shared by every instance
+    fields: RefCell<HashMap<String, Object>>,
+}
+
+impl Class {
+    pub(crate) fn new(
+        name: String,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<String, Rc<Function>>,
+        getters: HashMap<String, Rc<Function>>,
+        class_methods: HashMap<String, Rc<Function>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+            getters,
+            class_methods,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up a method on the class, walking up the superclass chain
+    pub(crate) fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.methods)
+    }
+
+    /// Looks up a getter on the class, walking up the superclass chain
+    pub(crate) fn find_getter(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.getters)
+    }
+
+    /// Looks up a method declared with `class`, walking up the superclass chain
+    pub(crate) fn find_class_method(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.class_methods)
+    }
+
+    fn find(
+        &self,
+        name: &str,
+        members: fn(&Class) -> &HashMap<String, Rc<Function>>,
+    ) -> Option<Rc<Function>> {
+        match members(self).get(name) {
+            Some(method) => Some(method.clone()),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find(name, members)),
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<Object> {
+        match self.fields.borrow().get(name) {
+            Some(value) => Some(value.clone()),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.field(name)),
+        }
+    }
+
+    /// Looks up a field of the class, falling back to a class method bound to
+    /// it. Both are inherited from superclasses
+    pub(crate) fn get(class: &Rc<Class>, name: &str) -> Result<Object, LoxError> {
+        if let Some(value) = class.field(name) {
+            return Ok(value);
+        }
+
+        match class.find_class_method(name) {
+            Some(method) => Ok(Object::Callable(Rc::new(
+                method.bind(Object::Class(class.clone())),
+            ))),
+            None => Err(LoxError::UndefinedProperty {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Sets a field on this class, shadowing any inherited one of that name
+    pub(crate) fn set(&self, name: &str, value: Object) {
+        self.fields.borrow_mut().insert(name.to_string(), value);
+    }
+
+    /// Calling a class creates a new instance, running `init` when the class
+    /// declares one
+    pub(crate) fn call(
+        class: &Rc<Class>,
+        interpreter: &mut interpreter::Interpreter,
+        args: &[Object],
+    ) -> Result<Object, LoxError> {
+        let instance = Object::Instance(Rc::new(RefCell::new(Instance::new(class.clone()))));
+
+        match class.find_method("init") {
+            Some(initializer) => {
+                initializer.bind(instance.clone()).call(interpreter, args)?;
+            }
+            None if !args.is_empty() => {
+                return Err(LoxError::ArityMismatch {
+                    name: class.name.clone(),
+                    expected: 0,
+                    found: args.len(),
+                })
+            }
+            None => (),
+        }
+
+        Ok(instance)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Methods and getters shared between classes that include the trait. They
+/// are copied into each including class, so `this` is bound to its instances
+/// as for the class's own methods, and `super` reaches the including class's
+/// superclass. That lets a trait's `init` chain up to the initializer it
+/// replaces, and a method wrap the one it overrides
+#[derive(Debug)]
+pub(crate) struct Trait {
+    pub(crate) name: String,
+    methods: HashMap<String, Rc<Function>>,
+    getters: HashMap<String, Rc<Function>>,
+}
+
+impl Trait {
+    pub(crate) fn new(
+        name: String,
+        methods: HashMap<String, Rc<Function>>,
+        getters: HashMap<String, Rc<Function>>,
+    ) -> Self {
+        Self {
+            name,
+            methods,
+            getters,
+        }
+    }
+
+    /// Adds the members of `traits` to a class's own methods and getters.
+    /// Whatever the class declares itself wins, while two traits offering a
+    /// name the class doesn't declare is ambiguous
+    pub(crate) fn include(
+        class: &str,
+        superclass: Option<&Rc<Class>>,
+        traits: &[Rc<Trait>],
+        methods: &mut HashMap<String, Rc<Function>>,
+        getters: &mut HashMap<String, Rc<Function>>,
+    ) -> Result<(), LoxError> {
+        let declared: HashSet<String> = methods.keys().chain(getters.keys()).cloned().collect();
+        let superclass = match superclass {
+            Some(superclass) => Object::Class(superclass.clone()),
+            None => Object::Null,
+        };
+        let mut providers: HashMap<&str, &Rc<Trait>> = HashMap::new();
+
+        for included in traits {
+            let members = included
+                .methods
+                .iter()
+                .map(|(name, method)| (name, method, false))
+                .chain(
+                    included
+                        .getters
+                        .iter()
+                        .map(|(name, getter)| (name, getter, true)),
+                );
+
+            for (name, member, is_getter) in members {
+                if declared.contains(name) {
+                    continue;
+                }
+                match providers.get(name.as_str()) {
+                    // Listing the same trait twice is harmless
+                    Some(provider) if Rc::ptr_eq(provider, included) => continue,
+                    Some(provider) => {
+                        return Err(LoxError::AmbiguousTraitMember {
+                            class: class.to_string(),
+                            name: name.clone(),
+                            first: provider.name.clone(),
+                            second: included.name.clone(),
+                        })
+                    }
+                    None => {
+                        providers.insert(name, included);
+                    }
+                }
+
+                let member = Rc::new(member.with_super(superclass.clone()));
+                if is_getter {
+                    getters.insert(name.clone(), member);
+                } else {
+                    methods.insert(name.clone(), member);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Trait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Instance {
+    pub(crate) class: Rc<Class>,
+    fields: HashMap<String, Object>,
+}
+
+impl Instance {
+    pub(crate) fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a field on the instance, falling back to a method bound to it
+    pub(crate) fn get(instance: &Rc<RefCell<Instance>>, name: &str) -> Result<Object, LoxError> {
+        if let Some(value) = instance.borrow().fields.get(name) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(name);
+        match method {
+            Some(method) => Ok(Object::Callable(Rc::new(
+                method.bind(Object::Instance(instance.clone())),
+            ))),
+            None => Err(LoxError::UndefinedProperty {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// The getter reading `name` runs, bound to the instance, unless a field
+    /// of that name shadows it
+    pub(crate) fn getter(instance: &Rc<RefCell<Instance>>, name: &str) -> Option<Function> {
+        let borrowed = instance.borrow();
+        if borrowed.fields.contains_key(name) {
+            return None;
+        }
+        borrowed
+            .class
+            .find_getter(name)
+            .map(|getter| getter.bind(Object::Instance(instance.clone())))
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: Object) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::object::Object;
+use crate::{loxerror::LoxError, object::Object};
 
 // An environment gives you access to scoped variables
 #[derive(Debug)]
@@ -16,4 +16,132 @@ impl Environment {
             values: HashMap::new(),
         }
     }
+
+    pub(crate) fn new_enclosed(parent: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            parent: Some(parent),
+            values: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn define(&mut self, name: &str, value: Object) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<Object, LoxError> {
+        match self.values.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => match &self.parent {
+                Some(parent) => parent.borrow().get(name),
+                None => Err(LoxError::UndefinedVariable {
+                    name: name.to_string(),
+                }),
+            },
+        }
+    }
+
+    pub(crate) fn assign(&mut self, name: &str, value: Object) -> Result<(), LoxError> {
+        if let Some(slot) = self.values.get_mut(name) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().assign(name, value),
+            None => Err(LoxError::UndefinedVariable {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Reads a variable from the environment `distance` scopes up the chain
+    pub(crate) fn get_at(&self, distance: usize, name: &str) -> Result<Object, LoxError> {
+        if distance == 0 {
+            return match self.values.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(LoxError::UndefinedVariable {
+                    name: name.to_string(),
+                }),
+            };
+        }
+
+        match &self.parent {
+            Some(parent) => parent.borrow().get_at(distance - 1, name),
+            None => Err(LoxError::UndefinedVariable {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Assigns a variable in the environment `distance` scopes up the chain
+    pub(crate) fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &str,
+        value: Object,
+    ) -> Result<(), LoxError> {
+        if distance == 0 {
+            return match self.values.get_mut(name) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(LoxError::UndefinedVariable {
+                    name: name.to_string(),
+                }),
+            };
+        }
+
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(LoxError::UndefinedVariable {
+                name: name.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn define_and_get() {
+        let mut env = Environment::new();
+        env.define("a", Object::Integer(1));
+        assert_eq!(env.get("a").unwrap().to_string(), "1");
+        assert!(env.get("b").is_err());
+    }
+
+    #[test]
+    fn lookup_through_parent() {
+        let parent = Rc::new(RefCell::new(Environment::new()));
+        parent.borrow_mut().define("a", Object::Integer(1));
+
+        let mut child = Environment::new_enclosed(parent.clone());
+        child.define("b", Object::Integer(2));
+        child.assign("a", Object::Integer(3)).unwrap();
+
+        assert_eq!(child.get("b").unwrap().to_string(), "2");
+        assert_eq!(parent.borrow().get("a").unwrap().to_string(), "3");
+        assert!(parent.borrow().get("b").is_err());
+        assert!(child.assign("c", Object::Null).is_err());
+    }
+
+    #[test]
+    fn lookup_at_distance() {
+        let parent = Rc::new(RefCell::new(Environment::new()));
+        parent.borrow_mut().define("a", Object::Integer(1));
+
+        let mut child = Environment::new_enclosed(parent.clone());
+        child.define("a", Object::Integer(2));
+
+        assert_eq!(child.get_at(0, "a").unwrap().to_string(), "2");
+        assert_eq!(child.get_at(1, "a").unwrap().to_string(), "1");
+        assert!(child.get_at(2, "a").is_err());
+
+        child.assign_at(1, "a", Object::Integer(3)).unwrap();
+        assert_eq!(parent.borrow().get("a").unwrap().to_string(), "3");
+        assert_eq!(child.get_at(0, "a").unwrap().to_string(), "2");
+    }
 }
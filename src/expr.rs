@@ -1,6 +1,10 @@
-use std::fmt::Display;
+use std::{
+    cell::Cell,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
 
-use crate::loxerror::LoxError;
+use crate::{bigint::BigInt, loxerror::LoxError, statement::FunctionDeclaration};
 
 #[derive(Debug)]
 pub(crate) enum Expr {
@@ -8,8 +12,40 @@ pub(crate) enum Expr {
     Unary(Operator, Box<Expr>),
     Binary(Box<Expr>, Operator, Box<Expr>),
     Grouping(Box<Expr>),
+    Variable(String, Depth),
+    Assign(String, Box<Expr>, Depth),
+    Get(Box<Expr>, String),
+    Set(Box<Expr>, String, Box<Expr>),
+    This(Depth),
+    Super(String, Depth),
+    /// `target op= value`, where the target is a variable, field or subscript
+    /// whose location is only evaluated once
+    CompoundAssign(Box<Expr>, Operator, Box<Expr>),
+    /// `++`/`--` on a variable, field or subscript, yielding the updated value
+    /// when used as a prefix and the original one as a postfix
+    Increment {
+        target: Box<Expr>,
+        operator: Operator,
+        prefix: bool,
+    },
+    /// `cond ? a : b`
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// A postfix chain following a `?.`, skipped entirely once a `?.` finds
+    /// its receiver is nil
+    OptionalChain(Box<Expr>, Vec<ChainLink>),
+    List(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    Lambda(Rc<FunctionDeclaration>),
+    /// A string literal with embedded expressions, whose parts are converted
+    /// to strings and joined
+    Interpolation(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    SetIndex(Box<Expr>, Box<Expr>, Box<Expr>),
 
-    Call { callee: Box<Expr>, args: Vec<Expr> },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
 }
 
 impl Display for Expr {
@@ -19,7 +55,60 @@ impl Display for Expr {
             Expr::Unary(op, rhs) => write!(f, "{}{}", op, rhs),
             Expr::Binary(lhs, op, rhs) => write!(f, "{} {} {}", op, lhs, rhs),
             Expr::Grouping(expression) => write!(f, "({})", expression),
-            Expr::Call { callee, args } => write!(f, "{}({:?})", callee, args),
+            Expr::Variable(name, _) => write!(f, "{}", name),
+            Expr::Assign(name, value, _) => write!(f, "= {} {}", name, value),
+            Expr::Get(object, name) => write!(f, "{}.{}", object, name),
+            Expr::Set(object, name, value) => write!(f, "= {}.{} {}", object, name, value),
+            Expr::This(_) => write!(f, "this"),
+            Expr::Super(method, _) => write!(f, "super.{}", method),
+            Expr::CompoundAssign(target, operator, value) => {
+                write!(f, "{}= {} {}", operator, target, value)
+            }
+            Expr::Increment {
+                target,
+                operator,
+                prefix: true,
+            } => write!(f, "{}{}{}", operator, operator, target),
+            Expr::Increment {
+                target, operator, ..
+            } => write!(f, "{}{}{}", target, operator, operator),
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                write!(f, "? {} {} {}", condition, then_branch, else_branch)
+            }
+            Expr::OptionalChain(object, links) => {
+                write!(f, "{}", object)?;
+                links.iter().try_for_each(|link| write!(f, "{}", link))
+            }
+            Expr::List(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Expr::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Expr::Lambda(declaration) => write!(f, "fun ({})", declaration.args.join(", ")),
+            Expr::Interpolation(parts) => {
+                let parts: Vec<String> = parts
+                    .iter()
+                    .map(|part| match part {
+                        Expr::Literal(Literal::String(value)) => value.clone(),
+                        part => format!("${{{}}}", part),
+                    })
+                    .collect();
+                write!(f, "\"{}\"", parts.join(""))
+            }
+            Expr::Index(target, index) => write!(f, "{}[{}]", target, index),
+            Expr::SetIndex(target, index, value) => {
+                write!(f, "= {}[{}] {}", target, index, value)
+            }
+            Expr::Call { callee, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", callee, args.join(", "))
+            }
         };
         Ok(())
     }
@@ -32,7 +121,35 @@ impl Expr {
             Expr::Unary(op, expr) => visitor.visit_unary_expression(op, expr),
             Expr::Binary(lhs, op, rhs) => visitor.visit_binary_expression(lhs, op, rhs),
             Expr::Grouping(expr) => visitor.visit_grouping_expression(expr),
-            Expr::Call { callee, args } => visitor.visit_function_expression(callee, &args),
+            Expr::Variable(name, depth) => visitor.visit_variable_expression(name, depth),
+            Expr::Assign(name, value, depth) => visitor.visit_assign_expression(name, value, depth),
+            Expr::Get(object, name) => visitor.visit_get_expression(object, name),
+            Expr::Set(object, name, value) => visitor.visit_set_expression(object, name, value),
+            Expr::This(depth) => visitor.visit_this_expression(depth),
+            Expr::Super(method, depth) => visitor.visit_super_expression(method, depth),
+            Expr::CompoundAssign(target, operator, value) => {
+                visitor.visit_compound_assign_expression(target, operator, value)
+            }
+            Expr::Increment {
+                target,
+                operator,
+                prefix,
+            } => visitor.visit_increment_expression(target, operator, *prefix),
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                visitor.visit_conditional_expression(condition, then_branch, else_branch)
+            }
+            Expr::OptionalChain(object, links) => {
+                visitor.visit_optional_chain_expression(object, links)
+            }
+            Expr::List(elements) => visitor.visit_list_expression(elements),
+            Expr::Map(entries) => visitor.visit_map_expression(entries),
+            Expr::Lambda(declaration) => visitor.visit_lambda_expression(declaration),
+            Expr::Interpolation(parts) => visitor.visit_interpolation_expression(parts),
+            Expr::Index(target, index) => visitor.visit_index_expression(target, index),
+            Expr::SetIndex(target, index, value) => {
+                visitor.visit_set_index_expression(target, index, value)
+            }
+            Expr::Call { callee, args } => visitor.visit_function_expression(callee, args),
         }
     }
 }
@@ -47,13 +164,113 @@ pub(crate) trait Visitor<R> {
     fn visit_literal_expression(&self, literal: &Literal) -> Result<R, LoxError>;
     fn visit_unary_expression(&mut self, operator: &Operator, expr: &Expr) -> Result<R, LoxError>;
     fn visit_grouping_expression(&mut self, expr: &Expr) -> Result<R, LoxError>;
+    fn visit_variable_expression(&mut self, name: &str, depth: &Depth) -> Result<R, LoxError>;
+    fn visit_assign_expression(
+        &mut self,
+        name: &str,
+        value: &Expr,
+        depth: &Depth,
+    ) -> Result<R, LoxError>;
+    fn visit_get_expression(&mut self, object: &Expr, name: &str) -> Result<R, LoxError>;
+    fn visit_set_expression(
+        &mut self,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> Result<R, LoxError>;
+    fn visit_this_expression(&mut self, depth: &Depth) -> Result<R, LoxError>;
+    fn visit_super_expression(&mut self, method: &str, depth: &Depth) -> Result<R, LoxError>;
+    fn visit_compound_assign_expression(
+        &mut self,
+        target: &Expr,
+        operator: &Operator,
+        value: &Expr,
+    ) -> Result<R, LoxError>;
+    fn visit_increment_expression(
+        &mut self,
+        target: &Expr,
+        operator: &Operator,
+        prefix: bool,
+    ) -> Result<R, LoxError>;
+    fn visit_conditional_expression(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<R, LoxError>;
+    fn visit_optional_chain_expression(
+        &mut self,
+        object: &Expr,
+        links: &[ChainLink],
+    ) -> Result<R, LoxError>;
+    fn visit_list_expression(&mut self, elements: &[Expr]) -> Result<R, LoxError>;
+    fn visit_map_expression(&mut self, entries: &[(Expr, Expr)]) -> Result<R, LoxError>;
+    fn visit_interpolation_expression(&mut self, parts: &[Expr]) -> Result<R, LoxError>;
+    fn visit_lambda_expression(
+        &mut self,
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<R, LoxError>;
+    fn visit_index_expression(&mut self, target: &Expr, index: &Expr) -> Result<R, LoxError>;
+    fn visit_set_index_expression(
+        &mut self,
+        target: &Expr,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<R, LoxError>;
 
-    fn visit_function_expression(&mut self, callee: &Expr, args: &Vec<Expr>)
-        -> Result<R, LoxError>;
+    fn visit_function_expression(&mut self, callee: &Expr, args: &[Expr]) -> Result<R, LoxError>;
+}
+
+/// One step of an optional chain, applied to the value of the steps before it
+#[derive(Debug)]
+pub(crate) enum ChainLink {
+    Get { name: String, optional: bool },
+    Call(Vec<Expr>),
+    Index(Box<Expr>),
+}
+
+impl Display for ChainLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainLink::Get {
+                name,
+                optional: true,
+            } => write!(f, "?.{}", name),
+            ChainLink::Get { name, .. } => write!(f, ".{}", name),
+            ChainLink::Call(args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "({})", args.join(", "))
+            }
+            ChainLink::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+/// The number of scopes between a variable reference and its declaration, as
+/// worked out by the resolver. A reference left unresolved refers to a global
+#[derive(Default)]
+pub(crate) struct Depth(Cell<Option<usize>>);
+
+impl Depth {
+    pub(crate) fn get(&self) -> Option<usize> {
+        self.0.get()
+    }
+
+    pub(crate) fn set(&self, depth: usize) {
+        self.0.set(Some(depth));
+    }
+}
+
+impl Debug for Depth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Depth({:?})", self.get())
+    }
 }
 
 #[derive(Debug)]
 pub(crate) enum Literal {
+    Integer(i64),
+    BigInteger(BigInt),
     Number(f64),
     String(String),
     Bool(bool),
@@ -63,8 +280,10 @@ pub(crate) enum Literal {
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let _ = match self {
-            Literal::Number(val) => write!(f, "{}", val),
-            Literal::String(val) => write!(f, "{}", val),
+            Literal::Integer(val) => write!(f, "{}", val),
+            Literal::BigInteger(val) => write!(f, "{}", val),
+            Literal::Number(val) => write!(f, "{:?}", val),
+            Literal::String(val) => write!(f, "\"{}\"", val),
             Literal::Bool(val) => write!(f, "{val}"),
             Literal::Nil => write!(f, "nil"),
         };
@@ -84,10 +303,22 @@ pub(crate) enum Operator {
     Minus,
     Mult,
     Divide,
+    Modulo,
+    Power,
+    FloorDivide,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Coalesce,
 
     // TODO: Are these operators?
     Negate,
     Not,
+    BitNot,
 }
 
 impl Display for Operator {
@@ -103,9 +334,21 @@ impl Display for Operator {
             Operator::Minus => write!(f, "-"),
             Operator::Mult => write!(f, "*"),
             Operator::Divide => write!(f, "/"),
+            Operator::Modulo => write!(f, "%"),
+            Operator::Power => write!(f, "**"),
+            Operator::FloorDivide => write!(f, "~/"),
+            Operator::BitAnd => write!(f, "&"),
+            Operator::BitOr => write!(f, "|"),
+            Operator::BitXor => write!(f, "^"),
+            Operator::ShiftLeft => write!(f, "<<"),
+            Operator::ShiftRight => write!(f, ">>"),
+            Operator::And => write!(f, "and"),
+            Operator::Or => write!(f, "or"),
+            Operator::Coalesce => write!(f, "??"),
 
             Operator::Negate => write!(f, "-"),
             Operator::Not => write!(f, "!"),
+            Operator::BitNot => write!(f, "~"),
         };
 
         Ok(())
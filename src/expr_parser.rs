@@ -3,8 +3,10 @@ use tracing::trace;
 use core::panic;
 
 use crate::{
-    expr::{Expr, Literal, Operator},
-    string_indexer::StringIndexer,
+    expr::{ChainLink, Depth, Expr, Literal, Operator},
+    loxerror::LoxError,
+    statement_parser::StatementParser,
+    string_indexer::{StringId, StringIndexer},
     token::{Token, TokenStore},
 };
 
@@ -16,47 +18,201 @@ impl ExprParser {
         Self {}
     }
 
-    pub fn parse(&self, token_store: &mut TokenStore, string_indexer: &StringIndexer) -> Expr {
+    pub fn parse(
+        &self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Expr, LoxError> {
         parse_expression_binding_power(token_store, string_indexer, 0)
     }
 }
 
+/// Assignment is right associative and binds looser than any operator
+const ASSIGNMENT_BINDING_POWER: (u8, u8) = (2, 1);
+/// The conditional operator is right associative, binding just tighter than
+/// assignment so `a ? b : c ? d : e` nests in its else branch
+const CONDITIONAL_BINDING_POWER: (u8, u8) = (4, 3);
+/// Calls and property access bind tighter than any prefix or infix operator
+const CALL_BINDING_POWER: u8 = 29;
+
 fn parse_expression_binding_power(
     token_store: &mut TokenStore,
     string_indexer: &StringIndexer,
     min_binding_power: u8,
-) -> Expr {
+) -> Result<Expr, LoxError> {
     trace!("parse_expr_bp: {min_binding_power}");
 
     let mut lhs = match token_store.next() {
         Some(token) => match token {
+            Token::Integer(val) => Expr::Literal(Literal::Integer(*val)),
+            Token::BigInteger(val) => Expr::Literal(Literal::BigInteger(val.clone())),
             Token::Number(val) => Expr::Literal(Literal::Number(*val)),
             Token::String(string_id) => {
                 trace!("string_id: {}", string_id);
-                let value = string_indexer
-                    .get_string(string_id.clone())
-                    .expect("Should have value");
-                return parse_string(value);
+                let string_id = *string_id;
+                if token_store.is(Token::InterpolationStart) {
+                    parse_interpolation(token_store, string_indexer, string_id)?
+                } else {
+                    parse_string(string_indexer.get_string(string_id)?)
+                }
             }
-            Token::Minus => parse_unary(token_store, string_indexer, Operator::Negate),
-            Token::Bang => parse_unary(token_store, string_indexer, Operator::Not),
-            Token::LeftParen => parse_grouping(token_store, string_indexer),
-
+            Token::Identifier(string_id) => {
+                let name = string_indexer.get_string(*string_id)?.to_string();
+                Expr::Variable(name, Depth::default())
+            }
+            Token::Minus => parse_unary(token_store, string_indexer, Operator::Negate)?,
+            Token::PlusPlus => parse_prefix_increment(token_store, string_indexer, Operator::Plus)?,
+            Token::MinusMinus => {
+                parse_prefix_increment(token_store, string_indexer, Operator::Minus)?
+            }
+            Token::Bang => parse_unary(token_store, string_indexer, Operator::Not)?,
+            Token::Tilde => parse_unary(token_store, string_indexer, Operator::BitNot)?,
+            Token::LeftParen => parse_grouping(token_store, string_indexer)?,
+            Token::LeftBracket => parse_list(token_store, string_indexer)?,
+            Token::LeftBrace => parse_map(token_store, string_indexer)?,
+            // The body of a lambda is made of statements, which start out
+            // outside of any loop
+            Token::Fun => Expr::Lambda(StatementParser::new().lambda(token_store, string_indexer)?),
+
+            Token::This => Expr::This(Depth::default()),
+            Token::Super => parse_super(token_store, string_indexer)?,
             Token::True => Expr::Literal(Literal::Bool(true)),
             Token::False => Expr::Literal(Literal::Bool(false)),
-            t => unreachable!("Should not get here, invalid token: {:?}", t),
+            Token::Nil => Expr::Literal(Literal::Nil),
+            Token::Eof => return Err(LoxError::UnexpectedEof),
+            t => {
+                return Err(LoxError::InvalidStatement {
+                    error: format!("Expected an expression, found {:?}", t),
+                })
+            }
         },
-        None => panic!("No token found"),
+        None => return Err(LoxError::UnexpectedEof),
     };
     trace!("expression lhs: {lhs}");
 
     loop {
-        let op = match token_store.peek() {
-            Some(token) => match parse_operator(token) {
-                Some(op) => op,
-                None => break,
-            },
-            None => panic!("Expected a token operator"),
+        let token = match token_store.peek() {
+            Some(token) => token,
+            None => return Err(LoxError::UnexpectedEof),
+        };
+
+        match token {
+            Token::LeftParen => {
+                if CALL_BINDING_POWER < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+                lhs = parse_call(token_store, string_indexer, lhs)?;
+                continue;
+            }
+            Token::Dot => {
+                if CALL_BINDING_POWER < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+                let name = parse_property_name(token_store, string_indexer)?;
+                lhs = Expr::Get(Box::new(lhs), name);
+                continue;
+            }
+            Token::LeftBracket => {
+                if CALL_BINDING_POWER < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+                let index = parse_expression_binding_power(token_store, string_indexer, 0)?;
+                token_store.expect(Token::RightBracket)?;
+                token_store.consume();
+                lhs = Expr::Index(Box::new(lhs), Box::new(index));
+                continue;
+            }
+            Token::QuestionDot => {
+                if CALL_BINDING_POWER < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+                lhs = parse_optional_chain(token_store, string_indexer, lhs)?;
+                continue;
+            }
+            Token::Question => {
+                let (l_bind_power, r_bind_power) = CONDITIONAL_BINDING_POWER;
+                if l_bind_power < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+
+                let then_branch = parse_expression_binding_power(token_store, string_indexer, 0)?;
+                token_store.expect(Token::Colon)?;
+                token_store.consume();
+                let else_branch =
+                    parse_expression_binding_power(token_store, string_indexer, r_bind_power)?;
+                lhs =
+                    Expr::Conditional(Box::new(lhs), Box::new(then_branch), Box::new(else_branch));
+                continue;
+            }
+            Token::Equal => {
+                let (l_bind_power, r_bind_power) = ASSIGNMENT_BINDING_POWER;
+                if l_bind_power < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+
+                let value =
+                    parse_expression_binding_power(token_store, string_indexer, r_bind_power)?;
+                lhs = match lhs {
+                    Expr::Variable(name, _) => {
+                        Expr::Assign(name, Box::new(value), Depth::default())
+                    }
+                    Expr::Get(object, name) => Expr::Set(object, name, Box::new(value)),
+                    Expr::Index(list, index) => Expr::SetIndex(list, index, Box::new(value)),
+                    target => {
+                        return Err(LoxError::InvalidStatement {
+                            error: format!("Invalid assignment target: {}", target),
+                        })
+                    }
+                };
+                continue;
+            }
+            Token::PlusEqual | Token::MinusEqual | Token::StarEqual | Token::SlashEqual => {
+                let operator = match token {
+                    Token::PlusEqual => Operator::Plus,
+                    Token::MinusEqual => Operator::Minus,
+                    Token::StarEqual => Operator::Mult,
+                    _ => Operator::Divide,
+                };
+                let (l_bind_power, r_bind_power) = ASSIGNMENT_BINDING_POWER;
+                if l_bind_power < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+
+                let value =
+                    parse_expression_binding_power(token_store, string_indexer, r_bind_power)?;
+                lhs = Expr::CompoundAssign(Box::new(assignable(lhs)?), operator, Box::new(value));
+                continue;
+            }
+            Token::PlusPlus | Token::MinusMinus => {
+                let operator = match token {
+                    Token::PlusPlus => Operator::Plus,
+                    _ => Operator::Minus,
+                };
+                if CALL_BINDING_POWER < min_binding_power {
+                    break;
+                }
+                token_store.consume();
+
+                lhs = Expr::Increment {
+                    target: Box::new(assignable(lhs)?),
+                    operator,
+                    prefix: false,
+                };
+                continue;
+            }
+            _ => (),
+        }
+
+        let op = match parse_operator(token) {
+            Some(op) => op,
+            None => break,
         };
 
         let (l_bind_power, r_bind_power) = infix_binding_power(&op);
@@ -65,45 +221,282 @@ fn parse_expression_binding_power(
         }
         token_store.consume();
 
-        let rhs = parse_expression_binding_power(token_store, string_indexer, r_bind_power);
+        let rhs = parse_expression_binding_power(token_store, string_indexer, r_bind_power)?;
 
         lhs = Expr::Binary(Box::new(lhs), op, Box::new(rhs));
     }
     trace!("expression parse:{:?}", lhs);
-    lhs
+    Ok(lhs)
 }
 
 fn parse_string(value: &str) -> Expr {
     trace!("parse_string id: {value:?}");
 
-    match value {
-        "true" => Expr::Literal(Literal::Bool(true)),
-        "false" => Expr::Literal(Literal::Bool(false)),
-        "Nil" => Expr::Literal(Literal::Nil),
-        _ => Expr::Literal(Literal::String(value.to_string())),
+    // The indexed span includes the surrounding quotes, or for part of an
+    // interpolated string the `${` and `}` around its embedded expressions
+    let value = value.strip_prefix(['"', '}']).unwrap_or(value);
+    let value = value
+        .strip_suffix("${")
+        .or_else(|| value.strip_suffix('"'))
+        .unwrap_or(value);
+    Expr::Literal(Literal::String(value.to_string()))
+}
+
+/// Parses a string with embedded expressions, given its first literal segment
+fn parse_interpolation(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+    first: StringId,
+) -> Result<Expr, LoxError> {
+    trace!("parse_interpolation");
+
+    let mut parts = vec![parse_string(string_indexer.get_string(first)?)];
+    let mut segment = first;
+    while tokens.is(Token::InterpolationStart) {
+        tokens.consume();
+
+        // The segment so far runs from the opening quote to the `${`, so this
+        // is where the `$` sits counting the string's first character as 1
+        let position = parts.len() / 2 + 1;
+        let character = string_indexer.chars_between(first, segment)? - 2;
+        let expression = parse_expression_binding_power(tokens, string_indexer, 0)
+            .and_then(|expression| {
+                tokens.expect(Token::InterpolationEnd)?;
+                Ok(expression)
+            })
+            .map_err(|err| LoxError::InvalidStatement {
+                error: format!(
+                    "In interpolation {} of string, at character {}: {}",
+                    position, character, err
+                ),
+            })?;
+        tokens.consume();
+        parts.push(expression);
+
+        match tokens.next() {
+            Some(Token::String(string_id)) => {
+                segment = *string_id;
+                parts.push(parse_string(string_indexer.get_string(segment)?))
+            }
+            _ => {
+                return Err(LoxError::InvalidToken {
+                    error: "Expected the rest of an interpolated string",
+                })
+            }
+        }
     }
+
+    // Drop the empty text either side of adjacent or leading expressions
+    parts.retain(|part| !matches!(part, Expr::Literal(Literal::String(value)) if value.is_empty()));
+    Ok(Expr::Interpolation(parts))
 }
 
-fn parse_grouping(tokens: &mut TokenStore, string_indexer: &StringIndexer) -> Expr {
+fn parse_grouping(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+) -> Result<Expr, LoxError> {
     trace!("parse_grouping");
 
-    let expression = parse_expression_binding_power(tokens, string_indexer, 0);
+    let expression = parse_expression_binding_power(tokens, string_indexer, 0)?;
+    tokens.expect(Token::RightParen)?;
+    tokens.consume();
+    Ok(Expr::Grouping(Box::new(expression)))
+}
+
+/// Parses the rest of a postfix chain after a `?.`, so the whole chain can be
+/// skipped when a receiver is nil
+fn parse_optional_chain(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+    object: Expr,
+) -> Result<Expr, LoxError> {
+    trace!("parse_optional_chain object: {object}");
+
+    let mut links = vec![ChainLink::Get {
+        name: parse_property_name(tokens, string_indexer)?,
+        optional: true,
+    }];
+    loop {
+        let link = match tokens.peek() {
+            Some(Token::Dot) | Some(Token::QuestionDot) => {
+                let optional = tokens.is(Token::QuestionDot);
+                tokens.consume();
+                ChainLink::Get {
+                    name: parse_property_name(tokens, string_indexer)?,
+                    optional,
+                }
+            }
+            Some(Token::LeftParen) => {
+                tokens.consume();
+                ChainLink::Call(parse_arguments(tokens, string_indexer)?)
+            }
+            Some(Token::LeftBracket) => {
+                tokens.consume();
+                let index = parse_expression_binding_power(tokens, string_indexer, 0)?;
+                tokens.expect(Token::RightBracket)?;
+                tokens.consume();
+                ChainLink::Index(Box::new(index))
+            }
+            _ => break,
+        };
+        links.push(link);
+    }
+
+    Ok(Expr::OptionalChain(Box::new(object), links))
+}
+
+fn parse_property_name(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+) -> Result<String, LoxError> {
+    match tokens.next() {
+        Some(Token::Identifier(string_id)) => {
+            Ok(string_indexer.get_string(*string_id)?.to_string())
+        }
+        Some(_) => Err(LoxError::InvalidToken {
+            error: "Expected a property name after '.'",
+        }),
+        None => Err(LoxError::UnexpectedEof),
+    }
+}
+
+fn parse_list(tokens: &mut TokenStore, string_indexer: &StringIndexer) -> Result<Expr, LoxError> {
+    trace!("parse_list");
+
+    let mut elements = vec![];
+    while !tokens.is(Token::RightBracket) {
+        elements.push(parse_expression_binding_power(tokens, string_indexer, 0)?);
+
+        if !tokens.is(Token::Comma) {
+            break;
+        }
+        tokens.consume();
+    }
+    tokens.expect(Token::RightBracket)?;
+    tokens.consume();
+
+    Ok(Expr::List(elements))
+}
+
+fn parse_map(tokens: &mut TokenStore, string_indexer: &StringIndexer) -> Result<Expr, LoxError> {
+    trace!("parse_map");
+
+    let mut entries = vec![];
+    while !tokens.is(Token::RightBrace) {
+        let key = parse_expression_binding_power(tokens, string_indexer, 0)?;
+        tokens.expect(Token::Colon)?;
+        tokens.consume();
+        let value = parse_expression_binding_power(tokens, string_indexer, 0)?;
+        entries.push((key, value));
+
+        if !tokens.is(Token::Comma) {
+            break;
+        }
+        tokens.consume();
+    }
+    tokens.expect(Token::RightBrace)?;
     tokens.consume();
-    Expr::Grouping(Box::new(expression))
+
+    Ok(Expr::Map(entries))
 }
 
-fn parse_unary(tokens: &mut TokenStore, string_indexer: &StringIndexer, op: Operator) -> Expr {
+fn parse_unary(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+    op: Operator,
+) -> Result<Expr, LoxError> {
     trace!("parse_unary operator: {op}");
 
     let min_binding_power = prefix_binding_power(&op).1;
-    Expr::Unary(
+    Ok(Expr::Unary(
         op,
         Box::new(parse_expression_binding_power(
             tokens,
             string_indexer,
             min_binding_power,
+        )?),
+    ))
+}
+
+fn parse_prefix_increment(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+    operator: Operator,
+) -> Result<Expr, LoxError> {
+    trace!("parse_prefix_increment operator: {operator}");
+
+    let target = parse_expression_binding_power(tokens, string_indexer, CALL_BINDING_POWER)?;
+    Ok(Expr::Increment {
+        target: Box::new(assignable(target)?),
+        operator,
+        prefix: true,
+    })
+}
+
+/// Checks the target of a compound assignment or increment can be written to
+fn assignable(target: Expr) -> Result<Expr, LoxError> {
+    match target {
+        Expr::Variable(..) | Expr::Get(..) | Expr::Index(..) => Ok(target),
+        target => Err(LoxError::InvalidStatement {
+            error: format!("Invalid assignment target: {}", target),
+        }),
+    }
+}
+
+fn parse_super(tokens: &mut TokenStore, string_indexer: &StringIndexer) -> Result<Expr, LoxError> {
+    trace!("parse_super");
+
+    tokens.expect(Token::Dot)?;
+    tokens.consume();
+
+    match tokens.next() {
+        Some(Token::Identifier(string_id)) => Ok(Expr::Super(
+            string_indexer.get_string(*string_id)?.to_string(),
+            Depth::default(),
         )),
-    )
+        Some(_) => Err(LoxError::InvalidToken {
+            error: "Expected a superclass method name",
+        }),
+        None => Err(LoxError::UnexpectedEof),
+    }
+}
+
+fn parse_call(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+    callee: Expr,
+) -> Result<Expr, LoxError> {
+    trace!("parse_call callee: {callee}");
+
+    Ok(Expr::Call {
+        callee: Box::new(callee),
+        args: parse_arguments(tokens, string_indexer)?,
+    })
+}
+
+/// Parses the arguments of a call after its opening parenthesis
+fn parse_arguments(
+    tokens: &mut TokenStore,
+    string_indexer: &StringIndexer,
+) -> Result<Vec<Expr>, LoxError> {
+    let mut args = vec![];
+    while !tokens.is(Token::RightParen) {
+        if args.len() >= 255 {
+            return Err(LoxError::InvalidToken {
+                error: "Too many arguments to function call",
+            });
+        }
+        args.push(parse_expression_binding_power(tokens, string_indexer, 0)?);
+
+        if !tokens.is(Token::Comma) {
+            break;
+        }
+        tokens.consume();
+    }
+    tokens.expect(Token::RightParen)?;
+    tokens.consume();
+
+    Ok(args)
 }
 
 fn parse_operator(token: &Token) -> Option<Operator> {
@@ -113,6 +506,14 @@ fn parse_operator(token: &Token) -> Option<Operator> {
         Token::Minus => Some(Operator::Minus),
         Token::Slash => Some(Operator::Divide),
         Token::Star => Some(Operator::Mult),
+        Token::Percent => Some(Operator::Modulo),
+        Token::StarStar => Some(Operator::Power),
+        Token::TildeSlash => Some(Operator::FloorDivide),
+        Token::Ampersand => Some(Operator::BitAnd),
+        Token::Pipe => Some(Operator::BitOr),
+        Token::Caret => Some(Operator::BitXor),
+        Token::LessLess => Some(Operator::ShiftLeft),
+        Token::GreaterGreater => Some(Operator::ShiftRight),
 
         Token::EqualEqual => Some(Operator::EqualTo),
         Token::BangEqual => Some(Operator::NotEqualTo),
@@ -122,6 +523,10 @@ fn parse_operator(token: &Token) -> Option<Operator> {
         Token::Less => Some(Operator::LessThan),
         Token::LessEqual => Some(Operator::LessEqualThan),
 
+        Token::And => Some(Operator::And),
+        Token::Or => Some(Operator::Or),
+        Token::QuestionQuestion => Some(Operator::Coalesce),
+
         _t => None,
     };
     trace!("parse_operator: {op:?}");
@@ -132,15 +537,29 @@ fn infix_binding_power(op: &Operator) -> (u8, u8) {
     trace!("infix_bp: {op}");
 
     match op {
-        Operator::EqualTo | Operator::NotEqualTo => (1, 2),
+        Operator::Coalesce => (5, 6),
+        Operator::Or => (7, 8),
+        Operator::And => (9, 10),
+
+        Operator::EqualTo | Operator::NotEqualTo => (11, 12),
 
         Operator::LessThan
         | Operator::LessEqualThan
         | Operator::GreaterThan
-        | Operator::GreaterEqualThan => (3, 4),
-
-        Operator::Plus | Operator::Minus => (5, 6),
-        Operator::Mult | Operator::Divide => (7, 8),
+        | Operator::GreaterEqualThan => (13, 14),
+
+        // As in Python, bitwise operators bind tighter than comparisons so
+        // `flags & MASK == MASK` tests the masked bits
+        Operator::BitOr => (15, 16),
+        Operator::BitXor => (17, 18),
+        Operator::BitAnd => (19, 20),
+        Operator::ShiftLeft | Operator::ShiftRight => (21, 22),
+
+        Operator::Plus | Operator::Minus => (23, 24),
+        Operator::Mult | Operator::Divide | Operator::Modulo | Operator::FloorDivide => (25, 26),
+        // Right associative, and tighter than prefix operators so `-2 ** 2`
+        // negates the power
+        Operator::Power => (28, 27),
         _ => panic!("invalid infix operator: {:?}", op),
     }
 }
@@ -149,7 +568,7 @@ fn prefix_binding_power(op: &Operator) -> ((), u8) {
     trace!("prefix_bp: {op}");
 
     match op {
-        Operator::Negate | Operator::Not => ((), 9),
+        Operator::Negate | Operator::Not | Operator::BitNot => ((), 27),
         _ => panic!("invalid prefix operator: {:?}", op),
     }
 }
@@ -161,13 +580,14 @@ mod test {
 
     use super::*;
 
-    fn expr_parse_test<'a>(value: &'a str) -> String {
+    fn expr_parse_test(value: &str) -> String {
         let mut lexer = Lexer::new();
         let mut string_indexer = StringIndexer::new(value);
         let mut token_store = lexer.tokenize(&mut string_indexer, value).unwrap();
         let expr_parser = ExprParser::new();
         expr_parser
             .parse(&mut token_store, &string_indexer)
+            .unwrap()
             .to_string()
     }
 
@@ -195,6 +615,33 @@ mod test {
     #[test]
     fn test_precedence() {
         assert_eq!(expr_parse_test("1 + 2 * 3"), "+ 1 * 2 3");
+        assert_eq!(expr_parse_test("1 + 7 % 3 ~/ 2"), "+ 1 ~/ % 7 3 2");
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(expr_parse_test("a | b ^ c & d"), "| a ^ b & c d");
+        assert_eq!(expr_parse_test("flags & 4 == 4"), "== & flags 4 4");
+        assert_eq!(expr_parse_test("1 << 2 + 3"), "<< 1 + 2 3");
+        assert_eq!(expr_parse_test("a >> 1 < b"), "< >> a 1 b");
+        assert_eq!(expr_parse_test("~a & b"), "& ~a b");
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(expr_parse_test("2 ** 3 ** 2"), "** 2 ** 3 2");
+        assert_eq!(expr_parse_test("-2 ** 2"), "-** 2 2");
+        assert_eq!(expr_parse_test("2 ** -1"), "** 2 -1");
+        assert_eq!(expr_parse_test("2 * 3 ** 2"), "* 2 ** 3 2");
+        assert_eq!(expr_parse_test("++a ** 2"), "** ++a 2");
+    }
+
+    #[test]
+    fn test_logical() {
+        assert_eq!(expr_parse_test("a or b and c"), "or a and b c");
+        assert_eq!(expr_parse_test("a and b or c"), "or and a b c");
+        assert_eq!(expr_parse_test("a == b and c"), "and == a b c");
+        assert_eq!(expr_parse_test("x = a or b"), "= x or a b");
     }
 
     #[test]
@@ -210,8 +657,150 @@ mod test {
 
     #[test]
     fn test_function() {
-        let content = include_str!("../resources/function.lox");
-        let parsed = expr_parse_test(content);
-        assert_eq!(parsed, "");
+        assert_eq!(expr_parse_test("doPrint()"), "doPrint()");
+        assert_eq!(
+            expr_parse_test("doPrint(\"Mark\", 1 + 2)"),
+            "doPrint(\"Mark\", + 1 2)"
+        );
+        assert_eq!(expr_parse_test("curry(1)(2)"), "curry(1)(2)");
+    }
+
+    #[test]
+    fn test_property() {
+        assert_eq!(expr_parse_test("a.b.c"), "a.b.c");
+        assert_eq!(expr_parse_test("a.b(1).c"), "a.b(1).c");
+        assert_eq!(expr_parse_test("this.a = 1 + 2"), "= this.a + 1 2");
+        assert_eq!(expr_parse_test("-a.b"), "-a.b");
+        assert_eq!(expr_parse_test("super.method(1)"), "super.method(1)");
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(expr_parse_test("[]"), "[]");
+        assert_eq!(expr_parse_test("[1, 2 + 3, [4]]"), "[1, + 2 3, [4]]");
+        assert_eq!(expr_parse_test("xs[1 + 2]"), "xs[+ 1 2]");
+        assert_eq!(expr_parse_test("grid[0][1].a"), "grid[0][1].a");
+        assert_eq!(expr_parse_test("xs[0] = 1 + 2"), "= xs[0] + 1 2");
+    }
+
+    #[test]
+    fn test_map() {
+        assert_eq!(expr_parse_test("{}"), "{}");
+        assert_eq!(
+            expr_parse_test("{\"a\": 1, 2: [3], key: nil}"),
+            "{\"a\": 1, 2: [3], key: nil}"
+        );
+        assert_eq!(expr_parse_test("m[\"a\"] = 1"), "= m[\"a\"] 1");
+    }
+
+    #[test]
+    fn test_lambda() {
+        assert_eq!(expr_parse_test("fun () {}"), "fun ()");
+        assert_eq!(
+            expr_parse_test("apply(fun (a, b) { return a + b; }, 1)"),
+            "apply(fun (a, b), 1)"
+        );
+        assert_eq!(expr_parse_test("f = fun (x) => x * 2"), "= f fun (x)");
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(
+            expr_parse_test("\"hello ${name}, next ${age + 1}\""),
+            "\"hello ${name}, next ${+ age 1}\""
+        );
+        assert_eq!(expr_parse_test("\"${a}${b}\""), "\"${a}${b}\"");
+        assert_eq!(
+            expr_parse_test("\"${ {\"k\": \"${v}\"}[\"k\"] }!\""),
+            "\"${{\"k\": \"${v}\"}[\"k\"]}!\""
+        );
+        assert_eq!(expr_parse_test("\"}$\" + \"\""), "+ \"}$\" \"\"");
+    }
+
+    #[test]
+    fn test_invalid_interpolation() {
+        let value = "\"ok ${1} bad ${1 +}\"";
+        let mut lexer = Lexer::new();
+        let mut string_indexer = StringIndexer::new(value);
+        let mut token_store = lexer.tokenize(&mut string_indexer, value).unwrap();
+        let error = ExprParser::new()
+            .parse(&mut token_store, &string_indexer)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("In interpolation 2 of string, at character 13:"));
+
+        let value = "\"${[}\"";
+        let mut string_indexer = StringIndexer::new(value);
+        let mut token_store = lexer.tokenize(&mut string_indexer, value).unwrap();
+        let error = ExprParser::new()
+            .parse(&mut token_store, &string_indexer)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("In interpolation 1 of string, at character 1:"));
+    }
+
+    #[test]
+    fn test_conditional() {
+        assert_eq!(expr_parse_test("a ? b : c"), "? a b c");
+        assert_eq!(expr_parse_test("a or b ? 1 + 2 : 3"), "? or a b + 1 2 3");
+        assert_eq!(expr_parse_test("a ? b : c ? d : e"), "? a b ? c d e");
+        assert_eq!(expr_parse_test("a ? b ? c : d : e"), "? a ? b c d e");
+        assert_eq!(expr_parse_test("x = a ? b : c"), "= x ? a b c");
+    }
+
+    #[test]
+    fn test_coalesce() {
+        assert_eq!(expr_parse_test("a ?? b ?? c"), "?? ?? a b c");
+        assert_eq!(expr_parse_test("a ?? b or c"), "?? a or b c");
+        assert_eq!(expr_parse_test("a ?? b ? c : d"), "? ?? a b c d");
+    }
+
+    #[test]
+    fn test_optional_chain() {
+        assert_eq!(expr_parse_test("a?.b"), "a?.b");
+        assert_eq!(expr_parse_test("a.b?.c(1)[2].d?.e"), "a.b?.c(1)[2].d?.e");
+        assert_eq!(expr_parse_test("-a?.b + 1"), "+ -a?.b 1");
+        assert_eq!(expr_parse_test("a?.b ?? c"), "?? a?.b c");
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(expr_parse_test("a = 1 + 2"), "= a + 1 2");
+        assert_eq!(expr_parse_test("a = b = c"), "= a = b c");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(expr_parse_test("a += 1 + 2"), "+= a + 1 2");
+        assert_eq!(expr_parse_test("a.b -= c = 2"), "-= a.b = c 2");
+        assert_eq!(expr_parse_test("a[0] *= 2"), "*= a[0] 2");
+        assert_eq!(expr_parse_test("a /= b /= 2"), "/= a /= b 2");
+    }
+
+    #[test]
+    fn test_increment() {
+        assert_eq!(expr_parse_test("++a"), "++a");
+        assert_eq!(expr_parse_test("a--"), "a--");
+        assert_eq!(expr_parse_test("-a.b++"), "-a.b++");
+        assert_eq!(expr_parse_test("--a[0] + 1"), "+ --a[0] 1");
+        assert_eq!(expr_parse_test("a++ - b"), "- a++ b");
+    }
+
+    #[test]
+    fn test_invalid_assignment() {
+        for value in ["1 + a = 2", "f() += 1", "++1", "a++ ++", "(a)--"] {
+            let mut lexer = Lexer::new();
+            let mut string_indexer = StringIndexer::new(value);
+            let mut token_store = lexer.tokenize(&mut string_indexer, value).unwrap();
+            let expr_parser = ExprParser::new();
+            assert!(
+                expr_parser
+                    .parse(&mut token_store, &string_indexer)
+                    .is_err(),
+                "{value}"
+            );
+        }
     }
 }
@@ -1,36 +1,133 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt::Display, rc::Rc};
 
-use crate::{environment::Environment, interpreter, loxerror::LoxError, object::Object, statement::Statement, token::Token};
+use crate::{
+    environment::Environment,
+    interpreter::{self, ControlFlow},
+    loxerror::LoxError,
+    object::Object,
+    statement::FunctionDeclaration,
+};
+
+/// Signature shared by every built-in function implemented in Rust
+pub(crate) type NativeFn = fn(&mut interpreter::Interpreter, &[Object]) -> Result<Object, LoxError>;
 
 #[derive(Debug)]
 pub(crate) enum Function {
     User {
-        name: Token,
-        args: Vec<Token>,
-        body: Vec<Statement>,
+        declaration: Rc<FunctionDeclaration>,
         parent: Rc<RefCell<Environment>>,
+        /// The globals of the file the function was declared in
+        globals: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    },
+    Native {
+        name: &'static str,
+        arity: usize,
+        function: NativeFn,
     },
 }
 
 impl Function {
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            Function::User { declaration, .. } => declaration.args.len(),
+            Function::Native { arity, .. } => *arity,
+        }
+    }
+
+    /// Creates a copy of the method whose environment has `this` bound to
+    /// the given instance
+    pub(crate) fn bind(&self, instance: Object) -> Function {
+        self.enclose("this", instance)
+    }
+
+    /// Creates a copy of a trait member whose environment has `super` bound
+    /// to the superclass of the class including it, or nil when it has none
+    pub(crate) fn with_super(&self, superclass: Object) -> Function {
+        self.enclose("super", superclass)
+    }
+
+    fn enclose(&self, name: &str, value: Object) -> Function {
+        match self {
+            Function::User {
+                declaration,
+                parent,
+                globals,
+                is_initializer,
+            } => {
+                let mut env = Environment::new_enclosed(parent.clone());
+                env.define(name, value);
+                Function::User {
+                    declaration: declaration.clone(),
+                    parent: Rc::new(RefCell::new(env)),
+                    globals: globals.clone(),
+                    is_initializer: *is_initializer,
+                }
+            }
+            Function::Native {
+                name,
+                arity,
+                function,
+            } => Function::Native {
+                name,
+                arity: *arity,
+                function: *function,
+            },
+        }
+    }
+
     pub(crate) fn call(
         &self,
         interpreter: &mut interpreter::Interpreter,
-        _args: &Vec<Object>,
+        args: &[Object],
     ) -> Result<Object, LoxError> {
+        if args.len() != self.arity() {
+            return Err(LoxError::ArityMismatch {
+                name: self.to_string(),
+                expected: self.arity(),
+                found: args.len(),
+            });
+        }
+
         match self {
             Function::User {
-                name: _name,
-                args: _args,
-                body,
-                parent: parent,
+                declaration,
+                parent,
+                globals,
+                is_initializer,
             } => {
-                let env = Rc::new(RefCell::new());
-                match interpreter.execute_block(body) {
-                    Ok(_) => Ok(Object::Null),
-                    Err(err) => Err(err),
+                let mut env = Environment::new_enclosed(parent.clone());
+                for (name, value) in declaration.args.iter().zip(args) {
+                    env.define(name, value.clone());
                 }
-            },
+
+                interpreter.enter_function(&declaration.name)?;
+                let previous_globals = interpreter.replace_globals(globals.clone());
+                let flow = interpreter.execute_block(&declaration.body, Rc::new(RefCell::new(env)));
+                interpreter.replace_globals(previous_globals);
+                interpreter.exit_function();
+                let flow = flow?;
+
+                // An initializer always hands back the instance it was bound to
+                if *is_initializer {
+                    return parent.borrow().get_at(0, "this");
+                }
+                match flow {
+                    ControlFlow::Return(value) => Ok(value),
+                    // The parser keeps break and continue inside their loops
+                    _ => Ok(Object::Null),
+                }
+            }
+            Function::Native { function, .. } => function(interpreter, args),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::User { declaration, .. } => write!(f, "<fn {}>", declaration.name),
+            Function::Native { name, .. } => write!(f, "<native fn {}>", name),
         }
     }
 }
@@ -1,29 +1,238 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
 use crate::{
-    expr::{self, Expr, Literal},
-    loxerror::LoxError,
+    bigint::BigInt,
+    class::{Class, Instance, Trait},
+    environment::Environment,
+    expr::{self, ChainLink, Depth, Expr, Literal},
+    function::{Function, NativeFn},
+    loxerror::{LoxError, ThrownValue},
+    map::{Map, MapKey},
+    module::{self, Module},
+    native,
     object::Object,
-    statement::{self, Statement}, token::Token,
+    statement::{
+        self, CatchClause, ClassDeclaration, FunctionDeclaration, Statement, TraitDeclaration,
+    },
 };
 
-pub(crate) struct Interpreter {}
+/// The most user function calls that may be running at once. Each one takes
+/// several visitor frames of native stack, so this keeps runaway recursion
+/// within an 8 MiB main thread even in a debug build
+const MAX_CALL_DEPTH: usize = 256;
+
+/// Describes how execution should proceed once a statement has completed
+#[derive(Debug)]
+pub(crate) enum ControlFlow {
+    Normal,
+    Return(Object),
+    /// Leaves the innermost loop, or the loop with the given label
+    Break(Option<String>),
+    /// Skips to the next iteration of the innermost loop, or the loop with the
+    /// given label
+    Continue(Option<String>),
+}
+
+impl ControlFlow {
+    /// Whether a `break` or `continue` is aimed at the loop with this label
+    fn targets(target: &Option<String>, label: Option<&str>) -> bool {
+        match target {
+            None => true,
+            Some(target) => label == Some(target.as_str()),
+        }
+    }
+}
+
+pub(crate) struct Interpreter {
+    /// Built-in definitions shared by the script and every module it imports
+    builtins: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    /// Names of the user functions currently being run, innermost last
+    call_stack: Vec<String>,
+    /// Where the error currently unwinding was raised, recorded by the
+    /// innermost statement it escapes and taken when it is caught
+    error_location: Option<String>,
+    /// The class of the values runtime errors are caught as
+    error_class: Rc<Class>,
+    /// Canonical paths of the script and the modules being imported, with
+    /// the file currently running last
+    importing: Vec<PathBuf>,
+    /// Every module imported so far, so each file only runs once
+    modules: HashMap<PathBuf, Object>,
+}
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {}
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+        let error_class = Rc::new(Class::new(
+            "Error".to_string(),
+            None,
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        ));
+        builtins
+            .borrow_mut()
+            .define("Error", Object::Class(error_class.clone()));
+        let globals = Rc::new(RefCell::new(Environment::new_enclosed(builtins.clone())));
+
+        let mut interpreter = Self {
+            builtins,
+            environment: globals.clone(),
+            globals,
+            call_stack: Vec::new(),
+            error_location: None,
+            error_class,
+            importing: Vec::new(),
+            modules: HashMap::new(),
+        };
+        native::register(&mut interpreter);
+        interpreter
+    }
+
+    /// Records the file the script was read from, which imports are resolved
+    /// relative to. Without one they are relative to the working directory
+    pub(crate) fn set_script(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.importing = vec![path];
+    }
+
+    /// Runs the module at `path` the first time it is imported, handing back
+    /// its cached namespace afterwards
+    fn import(&mut self, path: &str) -> Result<Object, LoxError> {
+        let directory = match self.importing.last().and_then(|file| file.parent()) {
+            Some(directory) => directory.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        let path =
+            fs::canonicalize(directory.join(path)).map_err(|err| LoxError::ImportFailed {
+                path: path.to_string(),
+                error: err.to_string(),
+            })?;
+
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(module.clone());
+        }
+        if self.importing.contains(&path) {
+            return Err(LoxError::CircularImport {
+                path: path.display().to_string(),
+            });
+        }
+
+        let statements = module::load(&path)?;
+
+        // The module runs against its own globals, layered over the built-ins
+        let globals = Rc::new(RefCell::new(Environment::new_enclosed(
+            self.builtins.clone(),
+        )));
+        let previous_globals = std::mem::replace(&mut self.globals, globals.clone());
+        let previous_environment = std::mem::replace(&mut self.environment, globals.clone());
+        self.importing.push(path.clone());
+
+        let result = self.run(&statements);
+
+        self.importing.pop();
+        self.environment = previous_environment;
+        self.globals = previous_globals;
+        result?;
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let module = Object::Module(Rc::new(Module::new(name, globals)));
+        self.modules.insert(path, module.clone());
+        Ok(module)
+    }
+
+    /// Records a call to a user function, refusing one nested deeper than
+    /// `MAX_CALL_DEPTH` before it can exhaust the native stack
+    pub(crate) fn enter_function(&mut self, name: &str) -> Result<(), LoxError> {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(LoxError::StackOverflow {
+                depth: MAX_CALL_DEPTH,
+            });
+        }
+        self.call_stack.push(name.to_string());
+        Ok(())
     }
 
-    pub fn run(&mut self, statements: &Vec<Statement>) -> Result<(), LoxError> {
+    pub(crate) fn exit_function(&mut self) {
+        self.call_stack.pop();
+    }
+
+    /// Switches the globals unresolved variables are looked up in, as calling
+    /// a function declared in another module requires
+    pub(crate) fn replace_globals(
+        &mut self,
+        globals: Rc<RefCell<Environment>>,
+    ) -> Rc<RefCell<Environment>> {
+        std::mem::replace(&mut self.globals, globals)
+    }
+
+    /// Turns an error into the value a `catch` clause sees. Thrown values are
+    /// handed over as is, while runtime errors become `Error` instances with
+    /// a `message` and the `location` they were raised in
+    fn caught_value(&mut self, error: LoxError) -> Object {
+        let location = self.error_location.take();
+        if let LoxError::Thrown { value } = error {
+            return value.0;
+        }
+
+        let mut instance = Instance::new(self.error_class.clone());
+        instance.set("message", Object::String(error.to_string()));
+        instance.set("location", Object::String(location.unwrap_or_default()));
+        Object::Instance(Rc::new(RefCell::new(instance)))
+    }
+
+    /// Makes a built-in function available to every script as a global
+    pub(crate) fn define_native(&mut self, name: &'static str, arity: usize, function: NativeFn) {
+        let native = Function::Native {
+            name,
+            arity,
+            function,
+        };
+        self.builtins
+            .borrow_mut()
+            .define(name, Object::Callable(Rc::new(native)));
+    }
+
+    #[cfg(test)]
+    pub(crate) fn global(&self, name: &str) -> Result<Object, LoxError> {
+        self.globals.borrow().get(name)
+    }
+
+    pub fn run(&mut self, statements: &[Statement]) -> Result<(), LoxError> {
+        // Nothing is unwinding yet, but an error that escaped an earlier run
+        // may have left its location behind
+        self.error_location = None;
         for statement in statements {
             self.execute(statement)?;
         }
         Ok(())
     }
 
-    fn execute(&mut self, statement: &Statement) -> Result<(), LoxError> {
-        statement.accept(self)
+    fn execute(&mut self, statement: &Statement) -> Result<ControlFlow, LoxError> {
+        let result = statement.accept(self);
+        if let (Err(_), None, Some(line)) = (&result, &self.error_location, statement.line()) {
+            let frame = match self.call_stack.last() {
+                Some(name) => format!("function {}", name),
+                None => "script".to_string(),
+            };
+            self.error_location = Some(format!("line {} in {}", line, frame));
+        }
+        result
     }
 
-    fn evaluate<'a>(&mut self, expr: &Expr) -> Result<Object, LoxError> {
+    fn evaluate(&mut self, expr: &Expr) -> Result<Object, LoxError> {
         expr.accept(self)
     }
 
@@ -32,9 +241,131 @@ impl Interpreter {
     // ------------------------------------------------
     fn add(&self, lhs: Object, rhs: Object) -> Result<Object, LoxError> {
         match (lhs, rhs) {
-            (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l + r)),
             (Object::String(l), Object::String(r)) => Ok(Object::String(format!("{l}{r}"))),
-            _ => Err(LoxError::InterpreterStatement),
+            (l, r) => self.arithmetic(&expr::Operator::Plus, l, r),
+        }
+    }
+
+    fn arithmetic(
+        &self,
+        operator: &expr::Operator,
+        lhs: Object,
+        rhs: Object,
+    ) -> Result<Object, LoxError> {
+        match (&lhs, &rhs) {
+            (Object::Integer(l), Object::Integer(r)) => integer_arithmetic(operator, *l, *r),
+            (
+                Object::Integer(_) | Object::BigInteger(_),
+                Object::Integer(_) | Object::BigInteger(_),
+            ) => big_arithmetic(operator, &big(&lhs), &big(&rhs)),
+            // Comparing an integer with a float is exact, as equality is,
+            // rather than rounding the integer to a float first
+            (Object::Integer(_) | Object::BigInteger(_), Object::Number(r))
+                if is_comparison(operator) =>
+            {
+                Ok(compare(operator, compare_exactly(&big(&lhs), *r)))
+            }
+            (Object::Number(l), Object::Integer(_) | Object::BigInteger(_))
+                if is_comparison(operator) =>
+            {
+                let ordering = compare_exactly(&big(&rhs), *l).map(Ordering::reverse);
+                Ok(compare(operator, ordering))
+            }
+            // Mixing an integer with a float promotes the integer
+            (
+                Object::Integer(_) | Object::BigInteger(_) | Object::Number(_),
+                Object::Integer(_) | Object::BigInteger(_) | Object::Number(_),
+            ) => float_arithmetic(operator, float(&lhs), float(&rhs))
+                .ok_or_else(|| invalid_operands(operator, &lhs, &rhs)),
+            _ => Err(invalid_operands(operator, &lhs, &rhs)),
+        }
+    }
+
+    /// Applies an arithmetic operator on behalf of a compound assignment or
+    /// increment
+    fn apply(
+        &self,
+        operator: &expr::Operator,
+        lhs: Object,
+        rhs: Object,
+    ) -> Result<Object, LoxError> {
+        match operator {
+            expr::Operator::Plus => self.add(lhs, rhs),
+            _ => self.arithmetic(operator, lhs, rhs),
+        }
+    }
+
+    /// Reads the variable, field or subscript `target` refers to and writes
+    /// back the result of `update`, evaluating the parts of the target only
+    /// once. Hands back both the original and the updated values
+    fn update(
+        &mut self,
+        target: &Expr,
+        update: impl FnOnce(&mut Self, Object) -> Result<Object, LoxError>,
+    ) -> Result<(Object, Object), LoxError> {
+        match target {
+            Expr::Variable(name, depth) => {
+                let original = expr::Visitor::visit_variable_expression(self, name, depth)?;
+                let updated = update(self, original.clone())?;
+                match depth.get() {
+                    Some(distance) => {
+                        self.environment
+                            .borrow_mut()
+                            .assign_at(distance, name, updated.clone())?
+                    }
+                    None => self.globals.borrow_mut().assign(name, updated.clone())?,
+                }
+                Ok((original, updated))
+            }
+            Expr::Get(object, name) => {
+                let object = self.evaluate(object)?;
+                let original = self.get_property(object.clone(), name)?;
+                let updated = update(self, original.clone())?;
+                set_property(&object, name, updated.clone())?;
+                Ok((original, updated))
+            }
+            Expr::Index(target, index) => {
+                let target = self.evaluate(target)?;
+                let index = self.evaluate(index)?;
+                let original = index_value(target.clone(), index.clone())?;
+                let updated = update(self, original.clone())?;
+                set_index(target, index, updated.clone())?;
+                Ok((original, updated))
+            }
+            // The parser only builds updates of assignable targets
+            target => Err(LoxError::InvalidStatement {
+                error: format!("Invalid assignment target: {}", target),
+            }),
+        }
+    }
+
+    /// Reads a property, running the getter for it when there is one
+    fn get_property(&mut self, object: Object, name: &str) -> Result<Object, LoxError> {
+        match object {
+            Object::Instance(instance) => match Instance::getter(&instance, name) {
+                Some(getter) => getter.call(self, &[]),
+                None => Instance::get(&instance, name),
+            },
+            Object::Class(class) => Class::get(&class, name),
+            Object::Module(module) => module.get(name),
+            _ => Err(LoxError::InvalidPropertyAccess {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Evaluates the arguments and calls the value with them
+    fn call(&mut self, callee: Object, args: &[Expr]) -> Result<Object, LoxError> {
+        let arg_values: Result<Vec<Object>, LoxError> =
+            args.iter().map(|arg| self.evaluate(arg)).collect();
+        let evaluated_args = arg_values?;
+
+        match callee {
+            Object::Callable(function) => function.call(self, &evaluated_args),
+            Object::Class(class) => Class::call(&class, self, &evaluated_args),
+            _ => Err(LoxError::NotCallable {
+                callee: callee.type_name(),
+            }),
         }
     }
 
@@ -46,10 +377,383 @@ impl Interpreter {
         }
     }
 
-    pub(crate) fn execute_block(&mut self, statements: &Vec<Statement>) -> Result<(), LoxError> {
-        Ok(for statement in statements {
-            self.execute(statement)?
+    /// Executes the statements within the given environment, restoring the
+    /// current environment once done regardless of the outcome. Execution stops
+    /// early when a statement unwinds, e.g. a `return`
+    pub(crate) fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<ControlFlow, LoxError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let mut result = Ok(ControlFlow::Normal);
+        for statement in statements {
+            result = self.execute(statement);
+            if !matches!(result, Ok(ControlFlow::Normal)) {
+                break;
+            }
+        }
+
+        self.environment = previous;
+        result
+    }
+}
+
+/// Turns method declarations into functions closing over `environment`, keyed
+/// by name. A method called `initializer` returns `this` like `init` does
+fn methods(
+    declarations: &[Rc<FunctionDeclaration>],
+    environment: &Rc<RefCell<Environment>>,
+    globals: &Rc<RefCell<Environment>>,
+    initializer: Option<&str>,
+) -> HashMap<String, Rc<Function>> {
+    declarations
+        .iter()
+        .map(|method| {
+            let function = Function::User {
+                declaration: method.clone(),
+                parent: environment.clone(),
+                globals: globals.clone(),
+                is_initializer: Some(method.name.as_str()) == initializer,
+            };
+            (method.name.clone(), Rc::new(function))
         })
+        .collect()
+}
+
+fn set_property(object: &Object, name: &str, value: Object) -> Result<(), LoxError> {
+    match object {
+        Object::Instance(instance) => instance.borrow_mut().set(name, value),
+        Object::Class(class) => class.set(name, value),
+        _ => {
+            return Err(LoxError::InvalidPropertyAccess {
+                name: name.to_string(),
+            })
+        }
+    }
+    Ok(())
+}
+
+fn index_value(target: Object, index: Object) -> Result<Object, LoxError> {
+    match target {
+        Object::List(list) => {
+            let elements = list.borrow();
+            let index = list_index(&index, elements.len())?;
+            Ok(elements[index].clone())
+        }
+        Object::Map(map) => match map.borrow().get(&MapKey::try_from(&index)?) {
+            Some(value) => Ok(value.clone()),
+            None => Err(LoxError::MissingKey {
+                key: index.to_string(),
+            }),
+        },
+        other => Err(LoxError::NotIndexable {
+            target: other.type_name(),
+        }),
+    }
+}
+
+fn set_index(target: Object, index: Object, value: Object) -> Result<(), LoxError> {
+    match target {
+        Object::List(list) => {
+            let mut elements = list.borrow_mut();
+            let index = list_index(&index, elements.len())?;
+            elements[index] = value;
+        }
+        Object::Map(map) => {
+            let key = MapKey::try_from(&index)?;
+            map.borrow_mut().insert(key, value);
+        }
+        other => {
+            return Err(LoxError::NotIndexable {
+                target: other.type_name(),
+            })
+        }
+    }
+    Ok(())
+}
+
+/// Checks that an index is a whole number within the bounds of a list
+fn list_index(index: &Object, length: usize) -> Result<usize, LoxError> {
+    let value = match index {
+        Object::Integer(value) if *value >= 0 => *value as usize,
+        Object::Number(value) if *value >= 0.0 && value.fract() == 0.0 => *value as usize,
+        _ => {
+            return Err(LoxError::InvalidIndex {
+                index: index.to_string(),
+            })
+        }
+    };
+
+    if value >= length {
+        return Err(LoxError::IndexOutOfBounds {
+            index: value,
+            length,
+        });
+    }
+    Ok(value)
+}
+
+/// Applies an arithmetic or comparison operator to two integers, redoing it
+/// with big integers when the result doesn't fit in an `i64`
+fn integer_arithmetic(operator: &expr::Operator, l: i64, r: i64) -> Result<Object, LoxError> {
+    let result = match operator {
+        expr::Operator::Plus => l.checked_add(r),
+        expr::Operator::Minus => l.checked_sub(r),
+        expr::Operator::Mult => l.checked_mul(r),
+        // True division always gives a float, `~/` keeps to integers
+        expr::Operator::Divide => return Ok(Object::Number(l as f64 / r as f64)),
+        expr::Operator::Modulo | expr::Operator::FloorDivide if r == 0 => {
+            return Err(LoxError::DivisionByZero {
+                operator: operator.to_string(),
+            })
+        }
+        expr::Operator::Modulo => {
+            let remainder = l.wrapping_rem(r);
+            if remainder != 0 && (remainder < 0) != (r < 0) {
+                Some(remainder + r)
+            } else {
+                Some(remainder)
+            }
+        }
+        expr::Operator::FloorDivide => l.checked_div(r).map(|quotient| {
+            if l.wrapping_rem(r) != 0 && (l < 0) != (r < 0) {
+                quotient - 1
+            } else {
+                quotient
+            }
+        }),
+        // A negative exponent can't give an integer
+        expr::Operator::Power if r < 0 => return Ok(Object::Number((l as f64).powf(r as f64))),
+        expr::Operator::Power => u32::try_from(r)
+            .ok()
+            .and_then(|exponent| l.checked_pow(exponent)),
+        expr::Operator::LessThan => return Ok(Object::Boolean(l < r)),
+        expr::Operator::LessEqualThan => return Ok(Object::Boolean(l <= r)),
+        expr::Operator::GreaterThan => return Ok(Object::Boolean(l > r)),
+        expr::Operator::GreaterEqualThan => return Ok(Object::Boolean(l >= r)),
+        _ => {
+            return Err(invalid_operands(
+                operator,
+                &Object::Integer(l),
+                &Object::Integer(r),
+            ))
+        }
+    };
+
+    match result {
+        Some(result) => Ok(Object::Integer(result)),
+        None => big_arithmetic(operator, &BigInt::from(l), &BigInt::from(r)),
+    }
+}
+
+/// The most bits an integer may be computed with, about 80,000 digits. Not a
+/// limit of the representation, but a guard against arithmetic that would
+/// take too long or too much memory to finish
+const MAX_INTEGER_BITS: u64 = 1 << 18;
+
+/// Applies an arithmetic or comparison operator to integers of any size,
+/// narrowing the result back to an `i64` when it fits
+fn big_arithmetic(operator: &expr::Operator, l: &BigInt, r: &BigInt) -> Result<Object, LoxError> {
+    let too_large = || LoxError::ResultTooLarge {
+        operator: operator.to_string(),
+        bits: MAX_INTEGER_BITS,
+    };
+
+    let result = match operator {
+        expr::Operator::Plus => l + r,
+        expr::Operator::Minus => l - r,
+        expr::Operator::Mult => l * r,
+        expr::Operator::Divide => return Ok(Object::Number(l.to_f64() / r.to_f64())),
+        expr::Operator::Modulo | expr::Operator::FloorDivide => {
+            let (quotient, remainder) =
+                l.div_mod_floor(r).ok_or_else(|| LoxError::DivisionByZero {
+                    operator: operator.to_string(),
+                })?;
+            match operator {
+                expr::Operator::Modulo => remainder,
+                _ => quotient,
+            }
+        }
+        expr::Operator::Power if r.is_negative() => {
+            return Ok(Object::Number(l.to_f64().powf(r.to_f64())))
+        }
+        // 0, 1 and -1 stay small however large the exponent is
+        expr::Operator::Power if l.bits() <= 1 => {
+            if r.is_zero() || (l.is_negative() && !r.is_odd()) {
+                BigInt::from(1)
+            } else {
+                l.clone()
+            }
+        }
+        // Anything else has more than `(bits - 1) * exponent` bits, so a
+        // result sure to be too large is refused before working it out
+        expr::Operator::Power => match r.to_i64().and_then(|r| u32::try_from(r).ok()) {
+            Some(exponent) if (l.bits() - 1) * u64::from(exponent) < MAX_INTEGER_BITS => {
+                l.pow(exponent)
+            }
+            _ => return Err(too_large()),
+        },
+        expr::Operator::LessThan => return Ok(Object::Boolean(l < r)),
+        expr::Operator::LessEqualThan => return Ok(Object::Boolean(l <= r)),
+        expr::Operator::GreaterThan => return Ok(Object::Boolean(l > r)),
+        expr::Operator::GreaterEqualThan => return Ok(Object::Boolean(l >= r)),
+        _ => {
+            return Err(invalid_operands(
+                operator,
+                &Object::from(l.clone()),
+                &Object::from(r.clone()),
+            ))
+        }
+    };
+
+    if result.bits() > MAX_INTEGER_BITS {
+        return Err(too_large());
+    }
+    Ok(Object::from(result))
+}
+
+/// Applies an arithmetic or comparison operator to two floats, or `None` when
+/// the operator isn't one of those
+fn float_arithmetic(operator: &expr::Operator, l: f64, r: f64) -> Option<Object> {
+    let result = match operator {
+        expr::Operator::Plus => Object::Number(l + r),
+        expr::Operator::Minus => Object::Number(l - r),
+        expr::Operator::Mult => Object::Number(l * r),
+        expr::Operator::Divide => Object::Number(l / r),
+        // Like floor division, the remainder takes the sign of the divisor
+        expr::Operator::Modulo => {
+            let remainder = l % r;
+            if remainder != 0.0 && (remainder < 0.0) != (r < 0.0) {
+                Object::Number(remainder + r)
+            } else {
+                Object::Number(remainder)
+            }
+        }
+        expr::Operator::Power => Object::Number(l.powf(r)),
+        expr::Operator::FloorDivide => Object::Number((l / r).floor()),
+        expr::Operator::LessThan => Object::Boolean(l < r),
+        expr::Operator::LessEqualThan => Object::Boolean(l <= r),
+        expr::Operator::GreaterThan => Object::Boolean(l > r),
+        expr::Operator::GreaterEqualThan => Object::Boolean(l >= r),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn is_comparison(operator: &expr::Operator) -> bool {
+    matches!(
+        operator,
+        expr::Operator::LessThan
+            | expr::Operator::LessEqualThan
+            | expr::Operator::GreaterThan
+            | expr::Operator::GreaterEqualThan
+    )
+}
+
+/// Orders an integer against a float without rounding either, or `None` when
+/// the float is NaN
+fn compare_exactly(l: &BigInt, r: f64) -> Option<Ordering> {
+    if r.is_nan() {
+        return None;
+    }
+    if r.is_infinite() {
+        return Some(if r > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+    }
+
+    // A fractional float lies strictly between its floor and the next
+    // integer up, so the integer is below it exactly when it is at most the
+    // floor
+    let floor = BigInt::from_f64(r.floor())?;
+    Some(match l.cmp(&floor) {
+        Ordering::Equal if r.fract() != 0.0 => Ordering::Less,
+        ordering => ordering,
+    })
+}
+
+/// Turns the ordering of two operands into the result of a comparison
+/// operator, which is false for every operator when they are unordered
+fn compare(operator: &expr::Operator, ordering: Option<Ordering>) -> Object {
+    let result = ordering.is_some_and(|ordering| match operator {
+        expr::Operator::LessThan => ordering.is_lt(),
+        expr::Operator::LessEqualThan => ordering.is_le(),
+        expr::Operator::GreaterThan => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    });
+    Object::Boolean(result)
+}
+
+/// Widens an integer or float operand to a float
+fn float(value: &Object) -> f64 {
+    match value {
+        Object::Integer(value) => *value as f64,
+        Object::BigInteger(value) => value.to_f64(),
+        Object::Number(value) => *value,
+        _ => unreachable!("only numbers are widened"),
+    }
+}
+
+/// Widens an integer operand to a big integer
+fn big(value: &Object) -> BigInt {
+    match value {
+        Object::Integer(value) => BigInt::from(*value),
+        Object::BigInteger(value) => value.clone(),
+        _ => unreachable!("only integers are widened"),
+    }
+}
+
+/// Applies a bitwise operator, treating both operands as 64-bit integers
+fn bitwise(operator: &expr::Operator, lhs: Object, rhs: Object) -> Result<Object, LoxError> {
+    let (l, r) = match (&lhs, &rhs) {
+        (
+            Object::Integer(_) | Object::BigInteger(_) | Object::Number(_),
+            Object::Integer(_) | Object::BigInteger(_) | Object::Number(_),
+        ) => (integer(operator, &lhs)?, integer(operator, &rhs)?),
+        _ => return Err(invalid_operands(operator, &lhs, &rhs)),
+    };
+
+    let result = match operator {
+        expr::Operator::BitAnd => l & r,
+        expr::Operator::BitOr => l | r,
+        expr::Operator::BitXor => l ^ r,
+        expr::Operator::ShiftLeft | expr::Operator::ShiftRight if !(0..64).contains(&r) => {
+            return Err(LoxError::InvalidShift { amount: r })
+        }
+        // Bits shifted past either end are dropped, and `>>` keeps the sign
+        expr::Operator::ShiftLeft => l << r,
+        expr::Operator::ShiftRight => l >> r,
+        _ => return Err(invalid_operands(operator, &lhs, &rhs)),
+    };
+    Ok(Object::Integer(result))
+}
+
+/// Takes the integer a bitwise operator works on the bits of. Floats are
+/// accepted when whole and within the range of an `i64`
+fn integer(operator: &expr::Operator, value: &Object) -> Result<i64, LoxError> {
+    // 2^63 is exactly representable, unlike `i64::MAX`
+    let limit = -(i64::MIN as f64);
+    match value {
+        Object::Integer(value) => Ok(*value),
+        Object::Number(number) if number.fract() == 0.0 && *number >= -limit && *number < limit => {
+            Ok(*number as i64)
+        }
+        _ => Err(LoxError::NotAnInteger {
+            operator: operator.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn invalid_operands(operator: &expr::Operator, lhs: &Object, rhs: &Object) -> LoxError {
+    LoxError::InvalidOperands {
+        operator: operator.to_string(),
+        lhs: lhs.type_name(),
+        rhs: rhs.type_name(),
     }
 }
 
@@ -61,26 +765,53 @@ impl expr::Visitor<Object> for Interpreter {
         rhs: &Expr,
     ) -> Result<Object, LoxError> {
         let lhs = self.evaluate(lhs)?;
+
+        // Logical operators and `??` short-circuit, yielding whichever operand
+        // decided the result rather than a boolean
+        match operator {
+            expr::Operator::Or if self.truthy(&lhs) => return Ok(lhs),
+            expr::Operator::And if !self.truthy(&lhs) => return Ok(lhs),
+            expr::Operator::Coalesce if lhs != Object::Null => return Ok(lhs),
+            expr::Operator::Or | expr::Operator::And | expr::Operator::Coalesce => {
+                return self.evaluate(rhs)
+            }
+            _ => (),
+        }
+
         let rhs = self.evaluate(rhs)?;
 
         match operator {
-            expr::Operator::EqualTo => todo!(),
-            expr::Operator::NotEqualTo => todo!(),
-            expr::Operator::LessThan => todo!(),
-            expr::Operator::LessEqualThan => todo!(),
-            expr::Operator::GreaterThan => todo!(),
-            expr::Operator::GreaterEqualThan => todo!(),
+            expr::Operator::EqualTo => Ok(Object::Boolean(lhs == rhs)),
+            expr::Operator::NotEqualTo => Ok(Object::Boolean(lhs != rhs)),
             expr::Operator::Plus => self.add(lhs, rhs),
-            expr::Operator::Minus => todo!(),
-            expr::Operator::Mult => todo!(),
-            expr::Operator::Divide => todo!(),
-            expr::Operator::Negate => todo!(),
-            expr::Operator::Not => todo!(),
+            expr::Operator::LessThan
+            | expr::Operator::LessEqualThan
+            | expr::Operator::GreaterThan
+            | expr::Operator::GreaterEqualThan
+            | expr::Operator::Minus
+            | expr::Operator::Mult
+            | expr::Operator::Divide
+            | expr::Operator::Modulo
+            | expr::Operator::Power
+            | expr::Operator::FloorDivide => self.arithmetic(operator, lhs, rhs),
+            expr::Operator::BitAnd
+            | expr::Operator::BitOr
+            | expr::Operator::BitXor
+            | expr::Operator::ShiftLeft
+            | expr::Operator::ShiftRight => bitwise(operator, lhs, rhs),
+            expr::Operator::And | expr::Operator::Or | expr::Operator::Coalesce => {
+                unreachable!()
+            }
+            expr::Operator::Negate | expr::Operator::Not | expr::Operator::BitNot => {
+                Err(invalid_operands(operator, &lhs, &rhs))
+            }
         }
     }
 
     fn visit_literal_expression(&self, literal: &Literal) -> Result<Object, LoxError> {
         match literal {
+            Literal::Integer(val) => Ok(Object::Integer(*val)),
+            Literal::BigInteger(val) => Ok(Object::BigInteger(val.clone())),
             Literal::Number(val) => Ok(Object::Number(*val)),
             Literal::String(val) => Ok(Object::String(val.to_string())),
             Literal::Bool(val) => Ok(Object::Boolean(*val)),
@@ -96,14 +827,32 @@ impl expr::Visitor<Object> for Interpreter {
         let result = self.evaluate(expr)?;
         match operator {
             expr::Operator::Negate => match result {
-                Object::Number(num) => Ok(Object::Number(-num.clone())),
-                _ => Err(LoxError::InterpreterExpression),
+                // Only `i64::MIN` has no negation within range
+                Object::Integer(num) => Ok(num
+                    .checked_neg()
+                    .map(Object::Integer)
+                    .unwrap_or_else(|| Object::from(-&BigInt::from(num)))),
+                Object::BigInteger(num) => Ok(Object::from(-&num)),
+                Object::Number(num) => Ok(Object::Number(-num)),
+                _ => Err(LoxError::InvalidOperand {
+                    operator: operator.to_string(),
+                    operand: result.type_name(),
+                }),
             },
-            expr::Operator::Not => match result {
-                Object::Boolean(value) => Ok(Object::Boolean(!value)),
-                _ => Err(LoxError::InterpreterExpression),
+            expr::Operator::Not => Ok(Object::Boolean(!self.truthy(&result))),
+            expr::Operator::BitNot => match result {
+                Object::Integer(_) | Object::BigInteger(_) | Object::Number(_) => {
+                    Ok(Object::Integer(!integer(operator, &result)?))
+                }
+                _ => Err(LoxError::InvalidOperand {
+                    operator: operator.to_string(),
+                    operand: result.type_name(),
+                }),
             },
-            _ => Err(LoxError::InterpreterExpression),
+            _ => Err(LoxError::InvalidOperand {
+                operator: operator.to_string(),
+                operand: result.type_name(),
+            }),
         }
     }
 
@@ -111,71 +860,1882 @@ impl expr::Visitor<Object> for Interpreter {
         self.evaluate(expr)
     }
 
+    fn visit_variable_expression(&mut self, name: &str, depth: &Depth) -> Result<Object, LoxError> {
+        match depth.get() {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn visit_assign_expression(
+        &mut self,
+        name: &str,
+        value: &Expr,
+        depth: &Depth,
+    ) -> Result<Object, LoxError> {
+        let value = self.evaluate(value)?;
+        match depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, name, value.clone())?
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
     fn visit_function_expression(
         &mut self,
         callee: &Expr,
-        args: &Vec<Expr>,
+        args: &[Expr],
     ) -> Result<Object, LoxError> {
         let callee = self.evaluate(callee)?;
+        self.call(callee, args)
+    }
 
-        let arg_values: Result<Vec<Object>, LoxError> =
-            args.into_iter().map(|arg| self.evaluate(arg)).collect();
-        let evaluated_args = arg_values?;
+    fn visit_get_expression(&mut self, object: &Expr, name: &str) -> Result<Object, LoxError> {
+        let object = self.evaluate(object)?;
+        self.get_property(object, name)
+    }
 
-        match callee {
-            Object::Callable(function) => function.call(self, &evaluated_args),
-            _ => return Err(LoxError::InterpreterExpression),
+    fn visit_optional_chain_expression(
+        &mut self,
+        object: &Expr,
+        links: &[ChainLink],
+    ) -> Result<Object, LoxError> {
+        let mut value = self.evaluate(object)?;
+        for link in links {
+            value = match link {
+                // A nil receiver short-circuits the rest of the chain
+                ChainLink::Get { optional: true, .. } if value == Object::Null => {
+                    return Ok(Object::Null)
+                }
+                ChainLink::Get { name, .. } => self.get_property(value, name)?,
+                ChainLink::Call(args) => self.call(value, args)?,
+                ChainLink::Index(index) => {
+                    let index = self.evaluate(index)?;
+                    index_value(value, index)?
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    fn visit_conditional_expression(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<Object, LoxError> {
+        let condition = self.evaluate(condition)?;
+        if self.truthy(&condition) {
+            self.evaluate(then_branch)
+        } else {
+            self.evaluate(else_branch)
+        }
+    }
+
+    fn visit_set_expression(
+        &mut self,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> Result<Object, LoxError> {
+        let object = match self.evaluate(object)? {
+            object @ (Object::Instance(_) | Object::Class(_)) => object,
+            _ => {
+                return Err(LoxError::InvalidPropertyAccess {
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        let value = self.evaluate(value)?;
+        set_property(&object, name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_compound_assign_expression(
+        &mut self,
+        target: &Expr,
+        operator: &expr::Operator,
+        value: &Expr,
+    ) -> Result<Object, LoxError> {
+        let (_, updated) = self.update(target, |interpreter, current| {
+            let value = interpreter.evaluate(value)?;
+            interpreter.apply(operator, current, value)
+        })?;
+        Ok(updated)
+    }
+
+    fn visit_increment_expression(
+        &mut self,
+        target: &Expr,
+        operator: &expr::Operator,
+        prefix: bool,
+    ) -> Result<Object, LoxError> {
+        let (original, updated) = self.update(target, |interpreter, current| {
+            interpreter.apply(operator, current, Object::Integer(1))
+        })?;
+        Ok(if prefix { updated } else { original })
+    }
+
+    fn visit_list_expression(&mut self, elements: &[Expr]) -> Result<Object, LoxError> {
+        let values: Result<Vec<Object>, LoxError> = elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect();
+        Ok(Object::List(Rc::new(RefCell::new(values?))))
+    }
+
+    fn visit_lambda_expression(
+        &mut self,
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<Object, LoxError> {
+        let function = Function::User {
+            declaration: declaration.clone(),
+            parent: self.environment.clone(),
+            globals: self.globals.clone(),
+            is_initializer: false,
+        };
+        Ok(Object::Callable(Rc::new(function)))
+    }
+
+    fn visit_interpolation_expression(&mut self, parts: &[Expr]) -> Result<Object, LoxError> {
+        let mut value = String::new();
+        for part in parts {
+            value.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(Object::String(value))
+    }
+
+    fn visit_map_expression(&mut self, entries: &[(Expr, Expr)]) -> Result<Object, LoxError> {
+        let mut map = Map::new();
+        for (key, value) in entries {
+            let key = MapKey::try_from(&self.evaluate(key)?)?;
+            let value = self.evaluate(value)?;
+            map.insert(key, value);
+        }
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_index_expression(&mut self, target: &Expr, index: &Expr) -> Result<Object, LoxError> {
+        let target = self.evaluate(target)?;
+        let index = self.evaluate(index)?;
+        index_value(target, index)
+    }
+
+    fn visit_set_index_expression(
+        &mut self,
+        target: &Expr,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<Object, LoxError> {
+        let target = self.evaluate(target)?;
+        if !matches!(target, Object::List(_) | Object::Map(_)) {
+            return Err(LoxError::NotIndexable {
+                target: target.type_name(),
+            });
+        }
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+
+        set_index(target, index, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_this_expression(&mut self, depth: &Depth) -> Result<Object, LoxError> {
+        self.visit_variable_expression("this", depth)
+    }
+
+    fn visit_super_expression(&mut self, method: &str, depth: &Depth) -> Result<Object, LoxError> {
+        let distance = match depth.get() {
+            Some(distance) => distance,
+            None => {
+                return Err(LoxError::UndefinedVariable {
+                    name: "super".to_string(),
+                })
+            }
+        };
+
+        // `this` always lives in the scope just inside the one holding `super`
+        let superclass = self.environment.borrow().get_at(distance, "super")?;
+        let instance = self.environment.borrow().get_at(distance - 1, "this")?;
+
+        // Within a class method `this` is the class, so `super` reaches the
+        // superclass's class methods
+        let found = match (&superclass, &instance) {
+            // Only a trait member included into a class without a superclass
+            // gets this far with none
+            (Object::Null, _) => return Err(LoxError::NoSuperclass),
+            (Object::Class(class), Object::Class(_)) => class.find_class_method(method),
+            (Object::Class(class), _) => {
+                if let Some(getter) = class.find_getter(method) {
+                    return getter.bind(instance).call(self, &[]);
+                }
+                class.find_method(method)
+            }
+            _ => None,
+        };
+        match found {
+            Some(function) => Ok(Object::Callable(Rc::new(function.bind(instance)))),
+            None => Err(LoxError::UndefinedProperty {
+                name: method.to_string(),
+            }),
         }
     }
 }
 
-impl statement::Visitor<()> for Interpreter {
-    fn visit_print_statement<'output>(&mut self, expr: &Box<Expr>) -> Result<(), LoxError> {
+impl statement::Visitor<ControlFlow> for Interpreter {
+    fn visit_print_statement(&mut self, expr: &Expr) -> Result<ControlFlow, LoxError> {
         let result = self.evaluate(expr)?;
-        println!("{}", result.to_string());
-        Ok(())
+        println!("{}", result);
+        Ok(ControlFlow::Normal)
     }
 
-    fn visit_if_statement<'con, 'output>(
+    fn visit_if_statement(
         &mut self,
-        condition: &Box<Expr>,
-        if_branch: &Box<Statement>,
-        else_branch: &Option<Box<Statement>>,
-    ) -> Result<(), LoxError> {
+        condition: &Expr,
+        if_branch: &Statement,
+        else_branch: Option<&Statement>,
+    ) -> Result<ControlFlow, LoxError> {
         let condition = self.evaluate(condition)?;
         if self.truthy(&condition) {
             return self.execute(if_branch);
         } else if let Some(other) = else_branch {
-            self.execute(other)?;
+            return self.execute(other);
         }
 
-        Ok(())
+        Ok(ControlFlow::Normal)
     }
 
-    fn visit_expression_statement<'output>(&mut self, expr: &Box<Expr>) -> Result<(), LoxError> {
+    fn visit_while_statement(
+        &mut self,
+        condition: &Expr,
+        body: &Statement,
+        increment: Option<&Expr>,
+        label: Option<&str>,
+    ) -> Result<ControlFlow, LoxError> {
+        loop {
+            let condition = self.evaluate(condition)?;
+            if !self.truthy(&condition) {
+                break;
+            }
+            match self.execute(body)? {
+                ControlFlow::Normal => (),
+                ControlFlow::Break(target) if ControlFlow::targets(&target, label) => break,
+                ControlFlow::Continue(target) if ControlFlow::targets(&target, label) => (),
+                flow => return Ok(flow),
+            }
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(ControlFlow::Normal)
+    }
+
+    fn visit_expression_statement(&mut self, expr: &Expr) -> Result<ControlFlow, LoxError> {
         self.evaluate(expr)?;
-        Ok(())
+        Ok(ControlFlow::Normal)
     }
 
-    fn visit_block_statement(&mut self, statements: &Vec<Statement>) -> Result<(), LoxError> {
-        self.execute_block(statements)?;
-        Ok(())
+    fn visit_block_statement(&mut self, statements: &[Statement]) -> Result<ControlFlow, LoxError> {
+        let environment = Environment::new_enclosed(self.environment.clone());
+        self.execute_block(statements, Rc::new(RefCell::new(environment)))
     }
 
     fn visit_function_statement(
         &mut self,
-        name: &Token,
-        args: &Vec<Token>,
-        body: &Vec<Statement>,
-    ) -> Result<(), LoxError> {
-        Ok(())
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<ControlFlow, LoxError> {
+        let function = Function::User {
+            declaration: declaration.clone(),
+            parent: self.environment.clone(),
+            globals: self.globals.clone(),
+            is_initializer: false,
+        };
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name, Object::Callable(Rc::new(function)));
+        Ok(ControlFlow::Normal)
+    }
+
+    fn visit_class_statement(
+        &mut self,
+        declaration: &Rc<ClassDeclaration>,
+    ) -> Result<ControlFlow, LoxError> {
+        let superclass = match &declaration.superclass {
+            Some(expr) => match self.evaluate(expr)? {
+                Object::Class(class) => Some(class),
+                _ => {
+                    return Err(LoxError::InvalidSuperclass {
+                        name: expr.to_string(),
+                    })
+                }
+            },
+            None => None,
+        };
+
+        // Methods of a subclass close over an extra scope holding `super`
+        let method_environment = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::new_enclosed(self.environment.clone());
+                environment.define("super", Object::Class(superclass.clone()));
+                Rc::new(RefCell::new(environment))
+            }
+            None => self.environment.clone(),
+        };
+
+        let traits = declaration
+            .traits
+            .iter()
+            .map(|expr| match self.evaluate(expr)? {
+                Object::Trait(included) => Ok(included),
+                _ => Err(LoxError::InvalidTrait {
+                    name: expr.to_string(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let functions = |declarations: &[Rc<FunctionDeclaration>], initializer: Option<&str>| {
+            methods(
+                declarations,
+                &method_environment,
+                &self.globals,
+                initializer,
+            )
+        };
+        let mut class_methods = functions(&declaration.methods, Some("init"));
+        let mut getters = functions(&declaration.getters, None);
+        Trait::include(
+            &declaration.name,
+            superclass.as_ref(),
+            &traits,
+            &mut class_methods,
+            &mut getters,
+        )?;
+
+        let class = Rc::new(Class::new(
+            declaration.name.clone(),
+            superclass,
+            class_methods,
+            getters,
+            functions(&declaration.class_methods, None),
+        ));
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name, Object::Class(class.clone()));
+
+        // Initialisers run once the class exists, so they can refer to it
+        for (name, initializer) in &declaration.fields {
+            let value = match initializer {
+                Some(expr) => self.evaluate(expr)?,
+                None => Object::Null,
+            };
+            class.set(name, value);
+        }
+        Ok(ControlFlow::Normal)
+    }
+
+    fn visit_trait_statement(
+        &mut self,
+        declaration: &Rc<TraitDeclaration>,
+    ) -> Result<ControlFlow, LoxError> {
+        let included = Trait::new(
+            declaration.name.clone(),
+            methods(
+                &declaration.methods,
+                &self.environment,
+                &self.globals,
+                Some("init"),
+            ),
+            methods(&declaration.getters, &self.environment, &self.globals, None),
+        );
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name, Object::Trait(Rc::new(included)));
+        Ok(ControlFlow::Normal)
     }
 
     fn visit_var_statement(
-        &mut self, 
-        name: &Token, 
-        initializer: &Option<Expr>
-    ) -> Result<(), LoxError> {
-        Ok(())
+        &mut self,
+        name: &str,
+        initializer: Option<&Expr>,
+    ) -> Result<ControlFlow, LoxError> {
+        let value = match initializer {
+            Some(expr) => self.evaluate(expr)?,
+            None => Object::Null,
+        };
+
+        self.environment.borrow_mut().define(name, value);
+        Ok(ControlFlow::Normal)
+    }
+
+    fn visit_import_statement(&mut self, path: &str, name: &str) -> Result<ControlFlow, LoxError> {
+        let module = self.import(path)?;
+        self.environment.borrow_mut().define(name, module);
+        Ok(ControlFlow::Normal)
+    }
+
+    fn visit_throw_statement(&mut self, value: &Expr) -> Result<ControlFlow, LoxError> {
+        let value = self.evaluate(value)?;
+        Err(LoxError::Thrown {
+            value: ThrownValue(value),
+        })
+    }
+
+    fn visit_try_statement(
+        &mut self,
+        body: &[Statement],
+        catch: Option<&CatchClause>,
+        finally: Option<&[Statement]>,
+    ) -> Result<ControlFlow, LoxError> {
+        let mut result = self.visit_block_statement(body);
+
+        if let Some(catch) = catch {
+            result = match result {
+                Err(error) => {
+                    let value = self.caught_value(error);
+                    let mut environment = Environment::new_enclosed(self.environment.clone());
+                    environment.define(&catch.name, value);
+                    self.execute_block(&catch.body, Rc::new(RefCell::new(environment)))
+                }
+                flow => flow,
+            };
+        }
+
+        // A finally block that itself jumps away or fails takes precedence,
+        // otherwise any error still unwinding keeps its own location
+        if let Some(finally) = finally {
+            let location = self.error_location.take();
+            match self.visit_block_statement(finally)? {
+                ControlFlow::Normal => self.error_location = location,
+                flow => return Ok(flow),
+            }
+        }
+        result
+    }
+
+    fn visit_break_statement(&mut self, label: Option<&str>) -> Result<ControlFlow, LoxError> {
+        Ok(ControlFlow::Break(label.map(str::to_string)))
+    }
+
+    fn visit_continue_statement(&mut self, label: Option<&str>) -> Result<ControlFlow, LoxError> {
+        Ok(ControlFlow::Continue(label.map(str::to_string)))
+    }
+
+    fn visit_return_statement(&mut self, value: Option<&Expr>) -> Result<ControlFlow, LoxError> {
+        let value = match value {
+            Some(expr) => self.evaluate(expr)?,
+            None => Object::Null,
+        };
+        Ok(ControlFlow::Return(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        resolver::Resolver, statement_parser::StatementParser, string_indexer::StringIndexer,
+        tokenizer::Lexer,
+    };
+
+    use super::*;
+
+    fn run(source: &str) -> (Interpreter, Result<(), LoxError>) {
+        run_from(source, None)
+    }
+
+    /// Runs the source as if it were read from `script`
+    fn run_from(source: &str, script: Option<&Path>) -> (Interpreter, Result<(), LoxError>) {
+        let mut lexer = Lexer::new();
+        let mut string_indexer = StringIndexer::new(source);
+        let mut token_store = lexer.tokenize(&mut string_indexer, source).unwrap();
+        let statements = StatementParser::new()
+            .run(&mut token_store, &string_indexer)
+            .unwrap();
+        Resolver::new().resolve(&statements).unwrap();
+
+        let mut interpreter = Interpreter::new();
+        if let Some(script) = script {
+            interpreter.set_script(script);
+        }
+        let result = interpreter.run(&statements);
+        (interpreter, result)
+    }
+
+    fn modules_script() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/modules/main.lox")
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> String {
+        interpreter
+            .environment
+            .borrow()
+            .get(name)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_var_declaration() {
+        let (interpreter, result) = run("var a = 1; var b; var c = a + 2;");
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "a"), "1");
+        assert_eq!(global(&interpreter, "b"), "null");
+        assert_eq!(global(&interpreter, "c"), "3");
+    }
+
+    #[test]
+    fn test_assignment() {
+        let (interpreter, result) = run("var a = 1; var b = a = 2;");
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "a"), "2");
+        assert_eq!(global(&interpreter, "b"), "2");
+    }
+
+    #[test]
+    fn test_block_scope() {
+        let (interpreter, result) = run(r#"
+            var a = 1;
+            var b = 1;
+            {
+                var a = 10;
+                b = a + 1;
+                var c = 3;
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "a"), "1");
+        assert_eq!(global(&interpreter, "b"), "11");
+        assert!(interpreter.environment.borrow().get("c").is_err());
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let (_, result) = run("print a;");
+        assert!(matches!(result, Err(LoxError::UndefinedVariable { name }) if name == "a"));
+
+        let (_, result) = run("a = 1;");
+        assert!(matches!(result, Err(LoxError::UndefinedVariable { name }) if name == "a"));
+    }
+
+    #[test]
+    fn test_while() {
+        let (interpreter, result) = run(r#"
+            var running = true;
+            var count = 0;
+            while (running) {
+                count = count + 1;
+                running = false;
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "count"), "1");
+    }
+
+    #[test]
+    fn test_for() {
+        let (interpreter, result) = run(r#"
+            var done = false;
+            var total = 0;
+            for (var i = 5; !done; i = i + 1) {
+                total = total + i;
+                done = true;
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "total"), "5");
+        assert!(interpreter.environment.borrow().get("i").is_err());
+    }
+
+    #[test]
+    fn test_list() {
+        let (interpreter, result) = run(r#"
+            var xs = [1, 2, 3];
+            xs[0] = xs[1] + xs[2];
+            push(xs, "four");
+            var first = xs[0];
+            var shown = str(xs);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "first"), "5");
+        assert_eq!(global(&interpreter, "shown"), "[5, 2, 3, four]");
+    }
+
+    #[test]
+    fn test_list_aliasing() {
+        let (interpreter, result) = run(r#"
+            var xs = [];
+            var ys = xs;
+            push(ys, 1);
+            var size = len(xs);
+            var same = xs == ys;
+            var different = [] == [];
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "size"), "1");
+        assert_eq!(global(&interpreter, "same"), "true");
+        assert_eq!(global(&interpreter, "different"), "false");
+    }
+
+    #[test]
+    fn test_print_cycles() {
+        let (interpreter, result) = run(r#"
+            var xs = [1, 2];
+            push(xs, xs);
+            var list = str(xs);
+            var m = {"a": 1};
+            m["self"] = m;
+            push(xs, m);
+            var map = str(m);
+            var both = str(xs);
+            var inner = [1];
+            var repeated = str([inner, inner]);
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "list"), "[1, 2, [...]]");
+        assert_eq!(global(&interpreter, "map"), "{a: 1, self: {...}}");
+        assert_eq!(
+            global(&interpreter, "both"),
+            "[1, 2, [...], {a: 1, self: {...}}]"
+        );
+        assert_eq!(global(&interpreter, "repeated"), "[[1], [1]]");
+    }
+
+    #[test]
+    fn test_list_index_errors() {
+        let (_, result) = run("var xs = [1]; xs[1];");
+        assert!(matches!(
+            result,
+            Err(LoxError::IndexOutOfBounds {
+                index: 1,
+                length: 1
+            })
+        ));
+
+        let (_, result) = run("var xs = [1]; xs[0.5] = 2;");
+        assert!(matches!(result, Err(LoxError::InvalidIndex { .. })));
+
+        let (_, result) = run("var xs = [1]; xs[-1];");
+        assert!(matches!(result, Err(LoxError::InvalidIndex { .. })));
+
+        let (_, result) = run("var s = \"abc\"; s[0];");
+        assert!(matches!(
+            result,
+            Err(LoxError::NotIndexable { target: "string" })
+        ));
+    }
+
+    #[test]
+    fn test_map() {
+        let (interpreter, result) = run(r#"
+            var config = {"name": "lox", 1: "one", true: nil};
+            var alias = config;
+            alias["name"] = "loxrs";
+            config[-0] = "zero";
+            var name = config["name"];
+            var zero = config[0];
+            var shown = str(config);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "name"), "loxrs");
+        assert_eq!(global(&interpreter, "zero"), "zero");
+        assert_eq!(
+            global(&interpreter, "shown"),
+            "{name: loxrs, 1: one, true: null, 0: zero}"
+        );
+    }
+
+    #[test]
+    fn test_map_errors() {
+        let (_, result) = run("var m = {}; m[\"missing\"];");
+        assert!(matches!(result, Err(LoxError::MissingKey { .. })));
+
+        let (_, result) = run("var m = {[]: 1};");
+        assert!(matches!(
+            result,
+            Err(LoxError::InvalidMapKey { key: "list" })
+        ));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let (interpreter, result) = run(r#"
+            var name = "Ada";
+            var age = 36;
+            var greeting = "hello ${name}, you are ${age + 1}";
+            var nested = "${"[${[1, 2][1]}]"} and {braces}";
+            var empty = "${nil}";
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "greeting"), "hello Ada, you are 37");
+        assert_eq!(global(&interpreter, "nested"), "[2] and {braces}");
+        assert_eq!(global(&interpreter, "empty"), "null");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let (interpreter, result) = run(r#"
+            var total = 10;
+            total += 5;
+            total -= 3;
+            total *= 2;
+            total /= 4;
+            var greeting = "hello";
+            greeting += " world";
+
+            class Counter { init() { this.count = 0; } }
+            var counter = Counter();
+            counter.count += 2;
+            var counted = counter.count;
+
+            var calls = 0;
+            fun index() { calls += 1; return 0; }
+            var xs = [1];
+            xs[index()] += 10;
+            var first = xs[0];
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "total"), "6.0");
+        assert_eq!(global(&interpreter, "greeting"), "hello world");
+        assert_eq!(global(&interpreter, "counted"), "2");
+        assert_eq!(global(&interpreter, "first"), "11");
+        assert_eq!(global(&interpreter, "calls"), "1");
+    }
+
+    #[test]
+    fn test_increment() {
+        let (interpreter, result) = run(r#"
+            var i = 0;
+            var postfix = i++;
+            var prefix = ++i;
+            var decremented = i--;
+            var m = {"hits": 1};
+            m["hits"]++;
+            var hits = m["hits"];
+            fun local() { var n = 5; --n; return n; }
+            var fromLocal = local();
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "postfix"), "0");
+        assert_eq!(global(&interpreter, "prefix"), "2");
+        assert_eq!(global(&interpreter, "decremented"), "2");
+        assert_eq!(global(&interpreter, "i"), "1");
+        assert_eq!(global(&interpreter, "hits"), "2");
+        assert_eq!(global(&interpreter, "fromLocal"), "4");
+
+        let (_, result) = run("var s = \"a\"; s++;");
+        assert!(matches!(result, Err(LoxError::InvalidOperands { .. })));
+    }
+
+    #[test]
+    fn test_conditional() {
+        let (interpreter, result) = run(r#"
+            var calls = 0;
+            fun count() { calls = calls + 1; return calls; }
+            var picked = 2 > 1 ? "yes" : count();
+            var nested = false ? 1 : nil ? 2 : 3;
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "picked"), "yes");
+        assert_eq!(global(&interpreter, "nested"), "3");
+        assert_eq!(global(&interpreter, "calls"), "0");
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let (interpreter, result) = run(r#"
+            var calls = 0;
+            fun count() { calls = calls + 1; return "fallback"; }
+            var missing = nil ?? count();
+            var kept = false ?? count();
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "missing"), "fallback");
+        assert_eq!(global(&interpreter, "kept"), "false");
+        assert_eq!(global(&interpreter, "calls"), "1");
+    }
+
+    #[test]
+    fn test_optional_chain() {
+        let (interpreter, result) = run(r#"
+            class Node {
+                init(value) { this.value = value; this.next = nil; }
+                describe() { return "node " + str(this.value); }
+            }
+            var head = Node(1);
+            head.next = Node(2);
+            var second = head?.next?.value;
+            var third = head.next?.next?.value;
+            var described = head?.next.describe();
+            var skipped = head.next.next?.next.describe();
+            var none = nil;
+            var called = none?.describe();
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "second"), "2");
+        assert_eq!(global(&interpreter, "third"), "null");
+        assert_eq!(global(&interpreter, "described"), "node 2");
+        assert_eq!(global(&interpreter, "skipped"), "null");
+        assert_eq!(global(&interpreter, "called"), "null");
+
+        let (_, result) = run("var n = 1; n?.value;");
+        assert!(matches!(
+            result,
+            Err(LoxError::InvalidPropertyAccess { .. })
+        ));
+    }
+
+    #[test]
+    fn test_lambda() {
+        let (interpreter, result) = run(r#"
+            fun apply(f, value) { return f(value); }
+            fun adder(n) { return fun (x) => x + n; }
+
+            var double = fun (x) { return x * 2; };
+            var doubled = apply(double, 4);
+            var added = adder(10)(5);
+            var shown = str(double);
+            fun () { print "called"; };
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "doubled"), "8");
+        assert_eq!(global(&interpreter, "added"), "15");
+        assert_eq!(global(&interpreter, "shown"), "<fn lambda>");
+    }
+
+    #[test]
+    fn test_throw_catch() {
+        let (interpreter, result) = run(r#"
+            var caught;
+            var cleaned = false;
+            try {
+                throw "boom";
+            } catch (e) {
+                caught = e;
+            } finally {
+                cleaned = true;
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "caught"), "boom");
+        assert_eq!(global(&interpreter, "cleaned"), "true");
+    }
+
+    #[test]
+    fn test_catch_runtime_error() {
+        let (interpreter, result) = run(r#"
+            fun fail() { return 1 + nil; }
+            var message;
+            var location;
+            var kind;
+            try {
+                fail();
+            } catch (e) {
+                message = e.message;
+                location = e.location;
+                kind = e;
+            }
+            try { undefined; } catch (e) { location = location + ", " + e.location; }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(
+            global(&interpreter, "message"),
+            "Operator '+' can't be applied to an integer and a nil"
+        );
+        assert_eq!(
+            global(&interpreter, "location"),
+            "line 2 in function fail, line 13 in script"
+        );
+        assert_eq!(global(&interpreter, "kind"), "Error instance");
+    }
+
+    #[test]
+    fn test_error_location() {
+        let (interpreter, result) = run(r#"
+            fun inner() {
+                var x = 1;
+                print x;
+                x.field;
+            }
+            fun outer() { inner(); }
+            var nested;
+            try { outer(); } catch (e) { nested = e.location; }
+
+            // A finally block's own caught errors leave the one unwinding alone
+            var kept;
+            try {
+                try {
+                    [][0];
+                } finally {
+                    try { nil(); } catch (ignored) {}
+                }
+            } catch (e) {
+                kept = e.location;
+            }
+
+            // Frames are left as errors unwind, not only when they are caught
+            fun throws() { return nil.field; }
+            fun swallows() {
+                try { throws(); } catch (e) {}
+                return missing;
+            }
+            var unwound;
+            try { swallows(); } catch (e) { unwound = e.location; }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "nested"), "line 5 in function inner");
+        assert_eq!(global(&interpreter, "kept"), "line 15 in script");
+        assert_eq!(
+            global(&interpreter, "unwound"),
+            "line 27 in function swallows"
+        );
+
+        let (interpreter, result) = run("fun fail() { return 1 + nil; } fail();");
+        assert!(result.is_err());
+        assert!(interpreter.call_stack.is_empty());
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        // Test threads get a smaller stack than the main thread scripts run on
+        let thread = std::thread::Builder::new().stack_size(8 << 20);
+        let outcome = thread
+            .spawn(|| {
+                let (interpreter, result) = run(r#"
+                    fun f() { f(); }
+                    var message;
+                    try { f(); } catch (e) { message = e.message; }
+                    fun count(n) { return n == 0 ? 0 : 1 + count(n - 1); }
+                    var counted = count(200);
+                "#);
+                assert!(result.is_ok(), "{:?}", result);
+                assert!(interpreter.call_stack.is_empty());
+                (
+                    global(&interpreter, "message"),
+                    global(&interpreter, "counted"),
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(
+            outcome,
+            (
+                "Stack overflow, calls nested more than 256 deep".to_string(),
+                "200".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_finally() {
+        let (interpreter, result) = run(r#"
+            var log = "";
+            fun f() {
+                try {
+                    return "body";
+                } finally {
+                    log = log + "finally";
+                }
+            }
+            var returned = f();
+            for (var i = 0; i < 3; i = i + 1) {
+                try { break; } finally { log = log + "!"; }
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "returned"), "body");
+        assert_eq!(global(&interpreter, "log"), "finally!");
+
+        let (interpreter, result) = run(r#"
+            var cleaned = false;
+            try { throw 42; } finally { cleaned = true; }
+        "#);
+        assert!(
+            matches!(&result, Err(LoxError::Thrown { value }) if value.0 == Object::Number(42.0))
+        );
+        assert_eq!(result.unwrap_err().to_string(), "Uncaught exception: 42");
+        assert_eq!(global(&interpreter, "cleaned"), "true");
+    }
+
+    #[test]
+    fn test_import() {
+        let (interpreter, result) = run_from(
+            include_str!("../resources/modules/main.lox"),
+            Some(&modules_script()),
+        );
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "area"), "12");
+        assert_eq!(global(&interpreter, "cached"), "true");
+        assert_eq!(global(&interpreter, "shown"), "<module math>");
+
+        // Definitions inside the modules don't leak into the importing script
+        let (_, result) = run_from(
+            "import \"shapes.lox\" as shapes; square(2);",
+            Some(&modules_script()),
+        );
+        assert!(matches!(result, Err(LoxError::UndefinedVariable { .. })));
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let script = modules_script();
+
+        let (_, result) = run_from("import \"cycle_a.lox\" as a;", Some(&script));
+        assert!(matches!(result, Err(LoxError::CircularImport { .. })));
+
+        let (_, result) = run_from("import \"missing.lox\" as m;", Some(&script));
+        assert!(matches!(result, Err(LoxError::ImportFailed { .. })));
+
+        let (_, result) = run_from("import \"math.lox\" as math; math.cube;", Some(&script));
+        assert!(matches!(result, Err(LoxError::UndefinedProperty { .. })));
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let (interpreter, result) = run(r#"
+            var total = 0;
+            for (var i = 0; i < 10; i = i + 1) {
+                if (i == 2) continue;
+                if (i == 5) break;
+                total = total + i;
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "total"), "8");
+    }
+
+    #[test]
+    fn test_labelled_break_continue() {
+        let (interpreter, result) = run(r#"
+            var pairs = 0;
+            outer: for (var i = 0; i < 3; i = i + 1) {
+                for (var j = 0; j < 3; j = j + 1) {
+                    if (j == 1) continue outer;
+                    if (i == 2) break outer;
+                    pairs = pairs + 1;
+                }
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "pairs"), "2");
+    }
+
+    #[test]
+    fn test_return_unwinds_to_call() {
+        let (interpreter, result) = run(r#"
+            fun f() {
+                var done = false;
+                while (true) {
+                    {
+                        if (done) return "unwound";
+                        done = true;
+                    }
+                }
+                return "unreachable";
+            }
+            var result = f();
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "result"), "unwound");
+    }
+
+    #[test]
+    fn test_function_call() {
+        let (interpreter, result) = run(r#"
+            fun add(a, b) {
+                return a + b;
+            }
+            fun noReturn() {}
+            var sum = add(1, 2);
+            var nothing = noReturn();
+            var f = add;
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "sum"), "3");
+        assert_eq!(global(&interpreter, "nothing"), "null");
+        assert_eq!(global(&interpreter, "f"), "<fn add>");
+
+        let (_, result) = run("fun f(a) {} f(1, 2);");
+        assert!(matches!(
+            result,
+            Err(LoxError::ArityMismatch {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_closures() {
+        let (interpreter, result) = run(r#"
+            fun makeCounter() {
+                var i = 0;
+                fun count() {
+                    i = i + 1;
+                    return i;
+                }
+                return count;
+            }
+            var first = makeCounter();
+            var second = makeCounter();
+            first();
+            first();
+            var a = first();
+            var b = second();
+
+            fun apply(callback, value) {
+                return callback(value);
+            }
+            fun double(n) {
+                return n + n;
+            }
+            var c = apply(double, 4);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "a"), "3");
+        assert_eq!(global(&interpreter, "b"), "1");
+        assert_eq!(global(&interpreter, "c"), "8");
+    }
+
+    #[test]
+    fn test_closure_binding_ignores_later_shadowing() {
+        let (interpreter, result) = run(r#"
+            var a = "global";
+            var first;
+            var second;
+            {
+                fun showA() {
+                    return a;
+                }
+                first = showA();
+                var a = "block";
+                second = showA();
+            }
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "first"), "global");
+        assert_eq!(global(&interpreter, "second"), "global");
+    }
+
+    #[test]
+    fn test_class() {
+        let (interpreter, result) = run(r#"
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+
+                sum() {
+                    return this.x + this.y;
+                }
+
+                moveBy(dx) {
+                    this.x = this.x + dx;
+                    return this;
+                }
+            }
+
+            var p = Point(1, 2);
+            var before = p.sum();
+            var after = p.moveBy(10).sum();
+            var method = p.sum;
+            p.sum = 0;
+            var field = p.sum;
+            var bound = method();
+            var reinit = p.init(0, 0);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "Point"), "Point");
+        assert_eq!(global(&interpreter, "p"), "Point instance");
+        assert_eq!(global(&interpreter, "before"), "3");
+        assert_eq!(global(&interpreter, "after"), "13");
+        assert_eq!(global(&interpreter, "field"), "0");
+        assert_eq!(global(&interpreter, "bound"), "13");
+        assert_eq!(global(&interpreter, "reinit"), "Point instance");
+    }
+
+    #[test]
+    fn test_class_errors() {
+        let (_, result) = run("class Foo {} Foo().bar;");
+        assert!(matches!(result, Err(LoxError::UndefinedProperty { name }) if name == "bar"));
+
+        let (_, result) = run("var a = 1; a.b = 2;");
+        assert!(matches!(result, Err(LoxError::InvalidPropertyAccess { name }) if name == "b"));
+
+        let (_, result) = run("class Foo { init(a) {} } Foo();");
+        assert!(matches!(
+            result,
+            Err(LoxError::ArityMismatch {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+
+        let (_, result) = run("class Foo {} Foo(1);");
+        assert!(matches!(result, Err(LoxError::ArityMismatch { .. })));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let (interpreter, result) = run(r#"
+            class A {
+                init(name) {
+                    this.name = name;
+                }
+                method() {
+                    return "A method";
+                }
+                describe() {
+                    return "A " + this.name;
+                }
+            }
+
+            class B < A {
+                method() {
+                    return "B method";
+                }
+                describe() {
+                    return super.describe() + " via B";
+                }
+            }
+
+            class C < B {
+                test() {
+                    return super.method();
+                }
+            }
+
+            var c = C("c");
+            var inherited = c.name;
+            var overridden = c.method();
+            var viaSuper = c.test();
+            var chained = c.describe();
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "inherited"), "c");
+        assert_eq!(global(&interpreter, "overridden"), "B method");
+        assert_eq!(global(&interpreter, "viaSuper"), "B method");
+        assert_eq!(global(&interpreter, "chained"), "A c via B");
+    }
+
+    #[test]
+    fn test_class_members() {
+        let (interpreter, result) = run(r#"
+            class Config {
+                class var instance;
+                class var created = 0;
+
+                class shared() {
+                    if (this.instance == nil) {
+                        this.instance = Config();
+                        this.created += 1;
+                    }
+                    return this.instance;
+                }
+
+                init() {
+                    this.values = {};
+                }
+            }
+
+            class Rectangle {
+                class var unit = Rectangle(1, 1);
+
+                class square(size) {
+                    return Rectangle(size, size);
+                }
+
+                init(width, height) {
+                    this.width = width;
+                    this.height = height;
+                }
+
+                area {
+                    return this.width * this.height;
+                }
+            }
+
+            class Box < Rectangle {
+                class cube(size) {
+                    return super.square(size);
+                }
+
+                area {
+                    return super.area * 2;
+                }
+            }
+
+            var same = Config.shared() == Config.shared();
+            var created = Config.created;
+            var square = Rectangle.square(3).area;
+            var unit = Rectangle.unit.area;
+            var inheritedMethod = Box.square(2).area;
+            var inheritedField = Box.unit.area;
+            var viaSuper = Box.cube(2).area;
+            var superGetter = Box(2, 3).area;
+
+            var shadowed = Rectangle(2, 5);
+            shadowed.area = "field";
+            var shadowedArea = shadowed.area;
+
+            Box.unit = nil;
+            var boxUnit = Box.unit;
+            var rectangleUnit = Rectangle.unit.area;
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "same"), "true");
+        assert_eq!(global(&interpreter, "created"), "1");
+        assert_eq!(global(&interpreter, "square"), "9");
+        assert_eq!(global(&interpreter, "unit"), "1");
+        assert_eq!(global(&interpreter, "inheritedMethod"), "4");
+        assert_eq!(global(&interpreter, "inheritedField"), "1");
+        assert_eq!(global(&interpreter, "viaSuper"), "4");
+        assert_eq!(global(&interpreter, "superGetter"), "12");
+        assert_eq!(global(&interpreter, "shadowedArea"), "field");
+        assert_eq!(global(&interpreter, "boxUnit"), "null");
+        assert_eq!(global(&interpreter, "rectangleUnit"), "1");
+    }
+
+    #[test]
+    fn test_class_member_errors() {
+        // Class methods aren't reachable through instances, nor methods
+        // through the class
+        let (_, result) = run("class A { class make() {} } A().make();");
+        assert!(matches!(result, Err(LoxError::UndefinedProperty { name }) if name == "make"));
+        let (_, result) = run("class A { method() {} } A.method();");
+        assert!(matches!(result, Err(LoxError::UndefinedProperty { name }) if name == "method"));
+
+        // A getter's value is what gets called
+        let (_, result) = run("class A { value { return 1; } } A().value();");
+        assert!(matches!(result, Err(LoxError::NotCallable { .. })));
+    }
+
+    #[test]
+    fn test_traits() {
+        let (interpreter, result) = run(r#"
+            trait Named {
+                greet() { return "I am " + this.name; }
+                shout { return this.name + "!"; }
+                describe() { return "named"; }
+            }
+            trait Counted {
+                count() { return 2; }
+            }
+            class Base {
+                describe() { return "base"; }
+                kind() { return "base"; }
+            }
+            class Thing < Base with Named, Counted {
+                init(name) { this.name = name; }
+                describe() { return "thing, " + super.describe(); }
+            }
+            trait Kind {
+                kind() { return "kind"; }
+            }
+            class Child < Thing with Kind {
+                greet() { return super.greet() + " too"; }
+            }
+
+            var thing = Thing("box");
+            var greeting = thing.greet();
+            var shout = thing.shout;
+            var description = thing.describe();
+            var count = thing.count();
+            var kind = Child("cup").kind();
+            var child = Child("cup").greet();
+            var printed = str(Named) + " is a " + type(Named);
+
+            trait Sized {
+                init(size) {
+                    this.size = size;
+                    return;
+                }
+            }
+            class Crate with Sized {}
+            var size = Crate(3).size;
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "greeting"), "I am box");
+        assert_eq!(global(&interpreter, "shout"), "box!");
+        assert_eq!(global(&interpreter, "description"), "thing, base");
+        assert_eq!(global(&interpreter, "count"), "2");
+        assert_eq!(global(&interpreter, "kind"), "kind");
+        assert_eq!(global(&interpreter, "child"), "I am cup too");
+        assert_eq!(global(&interpreter, "printed"), "Named is a trait");
+        assert_eq!(global(&interpreter, "size"), "3");
+    }
+
+    #[test]
+    fn test_trait_super() {
+        let (interpreter, result) = run(r#"
+            class Base {
+                init(id) { this.id = id; }
+            }
+            trait Tagged {
+                init(id) {
+                    super.init(id);
+                    this.tag = "tag " + str(id);
+                }
+            }
+            class Item < Base with Tagged {}
+            var id = Item(1).id;
+            var tag = Item(1).tag;
+
+            class Speaker {
+                speak() { return "hi"; }
+            }
+            class Greeter {
+                speak() { return "hello"; }
+            }
+            trait Loud {
+                speak() { return super.speak() + "!"; }
+                shout { return super.speak() + "!!"; }
+            }
+            class LoudSpeaker < Speaker with Loud {}
+            class LoudGreeter < Greeter with Loud {}
+            var speech = LoudSpeaker().speak();
+            var greeting = LoudGreeter().speak();
+            var shout = LoudSpeaker().shout;
+
+            var asked;
+            {
+                var suffix = "?";
+                trait Asking {
+                    ask() { return super.speak() + suffix; }
+                }
+                class Asker < Speaker with Asking {}
+                asked = Asker().ask();
+            }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "id"), "1");
+        assert_eq!(global(&interpreter, "tag"), "tag 1");
+        assert_eq!(global(&interpreter, "speech"), "hi!");
+        assert_eq!(global(&interpreter, "greeting"), "hello!");
+        assert_eq!(global(&interpreter, "shout"), "hi!!");
+        assert_eq!(global(&interpreter, "asked"), "hi?");
+
+        let (_, result) = run(r#"
+            trait Loud {
+                speak() { return super.speak() + "!"; }
+            }
+            class Alone with Loud {}
+            Alone().speak();
+        "#);
+        assert!(matches!(result, Err(LoxError::NoSuperclass)));
+    }
+
+    #[test]
+    fn test_trait_errors() {
+        let (_, result) = run("trait A { f() {} } trait B { f() {} } class C with A, B {}");
+        assert!(matches!(
+            result,
+            Err(LoxError::AmbiguousTraitMember { class, name, first, second })
+                if class == "C" && name == "f" && first == "A" && second == "B"
+        ));
+
+        // Declaring the member in the class settles the conflict
+        let (_, result) = run("trait A { f() {} } trait B { f() {} } class C with A, B { f() {} }");
+        assert!(result.is_ok(), "{:?}", result);
+        let (_, result) = run("trait A { f() {} } class C with A, A {}");
+        assert!(result.is_ok(), "{:?}", result);
+
+        let (_, result) = run("class A {} class B with A {}");
+        assert!(matches!(result, Err(LoxError::InvalidTrait { name }) if name == "A"));
+    }
+
+    #[test]
+    fn test_invalid_superclass() {
+        let (_, result) = run("var NotAClass = 1; class Foo < NotAClass {}");
+        assert!(matches!(result, Err(LoxError::InvalidSuperclass { name }) if name == "NotAClass"));
+
+        let (_, result) =
+            run("class A {} class B < A { test() { return super.missing; } } B().test();");
+        assert!(matches!(result, Err(LoxError::UndefinedProperty { name }) if name == "missing"));
+    }
+
+    #[test]
+    fn test_logical() {
+        let (interpreter, result) = run(r#"
+            var calls = 0;
+            fun touch(value) {
+                calls = calls + 1;
+                return value;
+            }
+
+            var a = nil or "fallback";
+            var b = 1 or touch(2);
+            var c = false and touch(true);
+            var d = true and "last";
+            var e = nil and touch(1);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "a"), "fallback");
+        assert_eq!(global(&interpreter, "b"), "1");
+        assert_eq!(global(&interpreter, "c"), "false");
+        assert_eq!(global(&interpreter, "d"), "last");
+        assert_eq!(global(&interpreter, "e"), "null");
+        assert_eq!(global(&interpreter, "calls"), "0");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let (interpreter, result) = run(r#"
+            var sum = 1 + 2;
+            var difference = 5 - 7;
+            var product = 3 * 4;
+            var quotient = 7 / 2;
+            var precedence = 1 + 2 * 3 - 4 / 2;
+            var negated = -(1 + 2);
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "sum"), "3");
+        assert_eq!(global(&interpreter, "difference"), "-2");
+        assert_eq!(global(&interpreter, "product"), "12");
+        assert_eq!(global(&interpreter, "quotient"), "3.5");
+        assert_eq!(global(&interpreter, "precedence"), "5.0");
+        assert_eq!(global(&interpreter, "negated"), "-3");
+    }
+
+    #[test]
+    fn test_integers() {
+        let (interpreter, result) = run(r#"
+            var sum = 2 + 3;
+            var promoted = 2 + 0.5;
+            var whole = 2.0;
+            var quotient = 6 / 3;
+            var floor = -7 ~/ 2;
+            var remainder = -7 % 3;
+            var power = 3 ** 4;
+            var inverse = 2 ** -1;
+            var big = 9007199254740993;
+            var equal = 1 == 1.0;
+            var inexact = 9007199254740993 == 9007199254740992.0;
+            var found = has({9007199254740993: 1}, 9007199254740992.0);
+            var less = 1 < 1.5;
+            var above = 9007199254740993 > 9007199254740992.0;
+            var below = 9007199254740992.0 < 9007199254740993;
+            var atMost = 9007199254740993 <= 9007199254740992.0;
+            var atLeast = 9007199254740992 >= 9007199254740992.0;
+            var bigBelow = 100000000000000000001 > 100000000000000000000.0;
+            var fraction = -2 < -1.5 and -1 > -1.5 and !(-1 <= -1.5);
+            var infinite = 9223372036854775807 < 1 / 0;
+            var nan = 1 < 0 / 0.0 or 1 >= 0 / 0.0;
+            var counter = 0;
+            for (var i = 0; i < 3; i++) counter += 1;
+            var length = len([1, 2]);
+            var parsed = num("42");
+            var parsedFloat = num("4.5");
+            var key = {1: "one"}[1.0];
+            var index = [10, 20][1];
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "sum"), "5");
+        assert_eq!(global(&interpreter, "promoted"), "2.5");
+        assert_eq!(global(&interpreter, "whole"), "2.0");
+        assert_eq!(global(&interpreter, "quotient"), "2.0");
+        assert_eq!(global(&interpreter, "floor"), "-4");
+        assert_eq!(global(&interpreter, "remainder"), "2");
+        assert_eq!(global(&interpreter, "power"), "81");
+        assert_eq!(global(&interpreter, "inverse"), "0.5");
+        assert_eq!(global(&interpreter, "big"), "9007199254740993");
+        assert_eq!(global(&interpreter, "equal"), "true");
+        assert_eq!(global(&interpreter, "inexact"), "false");
+        assert_eq!(global(&interpreter, "found"), "false");
+        assert_eq!(global(&interpreter, "less"), "true");
+        assert_eq!(global(&interpreter, "above"), "true");
+        assert_eq!(global(&interpreter, "below"), "true");
+        assert_eq!(global(&interpreter, "atMost"), "false");
+        assert_eq!(global(&interpreter, "atLeast"), "true");
+        assert_eq!(global(&interpreter, "bigBelow"), "true");
+        assert_eq!(global(&interpreter, "fraction"), "true");
+        assert_eq!(global(&interpreter, "infinite"), "true");
+        assert_eq!(global(&interpreter, "nan"), "false");
+        assert_eq!(global(&interpreter, "counter"), "3");
+        assert_eq!(global(&interpreter, "length"), "2");
+        assert_eq!(global(&interpreter, "parsed"), "42");
+        assert_eq!(global(&interpreter, "parsedFloat"), "4.5");
+        assert_eq!(global(&interpreter, "key"), "one");
+        assert_eq!(global(&interpreter, "index"), "20");
+    }
+
+    #[test]
+    fn test_integer_errors() {
+        let (_, result) = run("2 ** 10000000000;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Result of '**' is too large, integers are limited to 262144 bits"
+        );
+        let (_, result) = run("2 ** 4000000000;");
+        assert!(matches!(result, Err(LoxError::ResultTooLarge { .. })));
+        let (_, result) = run("10 ** 100000;");
+        assert!(matches!(result, Err(LoxError::ResultTooLarge { .. })));
+
+        // Every operation shares the limit, however the value is built up
+        let (interpreter, result) = run(r#"
+            var n = 2 ** 262143;
+            var largest = n - 1 + n;
+            var product;
+            try { n * 2; } catch (e) { product = e.message; }
+            var sum;
+            try { n + n; } catch (e) { sum = e.message; }
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(
+            global(&interpreter, "product"),
+            "Result of '*' is too large, integers are limited to 262144 bits"
+        );
+        assert_eq!(
+            global(&interpreter, "sum"),
+            "Result of '+' is too large, integers are limited to 262144 bits"
+        );
+        let (_, result) = run("1 % 0;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Integer division by zero in '%'"
+        );
+        let (_, result) = run("1 ~/ 0;");
+        assert!(matches!(result, Err(LoxError::DivisionByZero { .. })));
+        let (_, result) = run("100000000000000000000 ~/ 0;");
+        assert!(matches!(result, Err(LoxError::DivisionByZero { .. })));
+        let (_, result) = run("100000000000000000000 & 1;");
+        assert!(matches!(result, Err(LoxError::NotAnInteger { .. })));
+
+        // Floats keep IEEE semantics
+        let (interpreter, result) =
+            run("var infinite = 1 / 0; var overflow = 9223372036854775807 + 1.0;");
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "infinite"), "inf");
+        assert_eq!(global(&interpreter, "overflow"), "9223372036854775808.0");
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let (interpreter, result) = run(r#"
+            var max = 9223372036854775807;
+            var promoted = max + 1;
+            var product = max * max;
+            var power = 2 ** 100;
+            var literal = 123456789012345678901234567890;
+            var sum = literal + literal;
+            var narrowed = promoted - 1;
+            var narrowedType = type(narrowed);
+            var bigType = type(power);
+            var min = -9223372036854775807 - 1;
+            var negatedMin = -min;
+            var floorQuotient = min ~/ -1;
+            var floor = -power ~/ 3;
+            var remainder = -power % 3;
+            var less = max < promoted;
+            var equal = power == 2 ** 100;
+            var floatEqual = power == 2.0 ** 100;
+            var mixed = power + 0.5;
+            var divided = power / 2;
+            var key = {power: "big"}[2.0 ** 100];
+            var parsed = num("-123456789012345678901234567890");
+            var counter = max;
+            counter++;
+            var one = 1 ** 10000000000;
+            var zero = 0 ** 10000000000;
+            var even = (-1) ** 10000000000;
+            var odd = (-1) ** 10000000001;
+            var empty = 0 ** 0;
+            var huge = 2 ** 100000;
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "promoted"), "9223372036854775808");
+        assert_eq!(
+            global(&interpreter, "product"),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(
+            global(&interpreter, "power"),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(
+            global(&interpreter, "sum"),
+            "246913578024691357802469135780"
+        );
+        assert_eq!(global(&interpreter, "narrowed"), "9223372036854775807");
+        assert_eq!(global(&interpreter, "one"), "1");
+        assert_eq!(global(&interpreter, "zero"), "0");
+        assert_eq!(global(&interpreter, "even"), "1");
+        assert_eq!(global(&interpreter, "odd"), "-1");
+        assert_eq!(global(&interpreter, "empty"), "1");
+        assert_eq!(global(&interpreter, "huge").len(), 30103);
+        assert_eq!(global(&interpreter, "narrowedType"), "integer");
+        assert_eq!(global(&interpreter, "bigType"), "integer");
+        assert_eq!(global(&interpreter, "negatedMin"), "9223372036854775808");
+        assert_eq!(global(&interpreter, "floorQuotient"), "9223372036854775808");
+        assert_eq!(
+            global(&interpreter, "floor"),
+            "-422550200076076467165567735126"
+        );
+        assert_eq!(global(&interpreter, "remainder"), "2");
+        assert_eq!(global(&interpreter, "less"), "true");
+        assert_eq!(global(&interpreter, "equal"), "true");
+        assert_eq!(global(&interpreter, "floatEqual"), "true");
+        assert_eq!(
+            global(&interpreter, "mixed"),
+            "1267650600228229401496703205376.0"
+        );
+        assert_eq!(
+            global(&interpreter, "divided"),
+            "633825300114114700748351602688.0"
+        );
+        assert_eq!(global(&interpreter, "key"), "big");
+        assert_eq!(
+            global(&interpreter, "parsed"),
+            "-123456789012345678901234567890"
+        );
+        assert_eq!(global(&interpreter, "counter"), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_modulo_power_floor_division() {
+        let (interpreter, result) = run(r#"
+            var remainder = 7 % 3;
+            var negativeRemainder = -7 % 3;
+            var negativeDivisor = 7 % -3;
+            var fractional = 5.5 % 2;
+            var power = 2 ** 10;
+            var rightAssociative = 2 ** 3 ** 2;
+            var negatedPower = -2 ** 2;
+            var root = 9 ** 0.5;
+            var floor = 7 ~/ 2;
+            var negativeFloor = -7 ~/ 2;
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "remainder"), "1");
+        assert_eq!(global(&interpreter, "negativeRemainder"), "2");
+        assert_eq!(global(&interpreter, "negativeDivisor"), "-2");
+        assert_eq!(global(&interpreter, "fractional"), "1.5");
+        assert_eq!(global(&interpreter, "power"), "1024");
+        assert_eq!(global(&interpreter, "rightAssociative"), "512");
+        assert_eq!(global(&interpreter, "negatedPower"), "-4");
+        assert_eq!(global(&interpreter, "root"), "3.0");
+        assert_eq!(global(&interpreter, "floor"), "3");
+        assert_eq!(global(&interpreter, "negativeFloor"), "-4");
+
+        let (_, result) = run("\"a\" % 2;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Operator '%' can't be applied to a string and an integer"
+        );
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let (interpreter, result) = run(r#"
+            var both = 12 & 10;
+            var either = 12 | 10;
+            var xor = 12 ^ 10;
+            var inverted = ~5;
+            var left = 1 << 4;
+            var right = -16 >> 2;
+            var masked = 6 & 4 == 4;
+            var flags = 0;
+            flags = flags | 1 << 3;
+        "#);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(global(&interpreter, "both"), "8");
+        assert_eq!(global(&interpreter, "either"), "14");
+        assert_eq!(global(&interpreter, "xor"), "6");
+        assert_eq!(global(&interpreter, "inverted"), "-6");
+        assert_eq!(global(&interpreter, "left"), "16");
+        assert_eq!(global(&interpreter, "right"), "-4");
+        assert_eq!(global(&interpreter, "masked"), "true");
+        assert_eq!(global(&interpreter, "flags"), "8");
+
+        let (_, result) = run("1.5 & 1;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Operator '&' needs integers within 64-bit range, found 1.5"
+        );
+        let (_, result) = run("1 | 10000000000000000000.0;");
+        assert!(matches!(result, Err(LoxError::NotAnInteger { .. })));
+        let (_, result) = run("1 << 64;");
+        assert!(matches!(result, Err(LoxError::InvalidShift { amount: 64 })));
+        let (_, result) = run("~\"a\";");
+        assert!(matches!(result, Err(LoxError::InvalidOperand { .. })));
+        let (_, result) = run("true ^ 1;");
+        assert!(matches!(result, Err(LoxError::InvalidOperands { .. })));
+    }
+
+    #[test]
+    fn test_comparison_and_equality() {
+        let (interpreter, result) = run(r#"
+            class Foo {}
+            var foo = Foo();
+            fun f() {}
+
+            var less = 1 < 2;
+            var lessEqual = 2 <= 2;
+            var greater = 1 > 2;
+            var greaterEqual = 3 >= 2;
+            var numbers = 1 == 1;
+            var strings = "a" == "a";
+            var mixed = 1 == "1";
+            var nils = nil == nil;
+            var nilFalse = nil == false;
+            var sameInstance = foo == foo;
+            var otherInstance = foo != Foo();
+            var sameFunction = f == f;
+            var not = !nil;
+        "#);
+        assert!(result.is_ok());
+        assert_eq!(global(&interpreter, "less"), "true");
+        assert_eq!(global(&interpreter, "lessEqual"), "true");
+        assert_eq!(global(&interpreter, "greater"), "false");
+        assert_eq!(global(&interpreter, "greaterEqual"), "true");
+        assert_eq!(global(&interpreter, "numbers"), "true");
+        assert_eq!(global(&interpreter, "strings"), "true");
+        assert_eq!(global(&interpreter, "mixed"), "false");
+        assert_eq!(global(&interpreter, "nils"), "true");
+        assert_eq!(global(&interpreter, "nilFalse"), "false");
+        assert_eq!(global(&interpreter, "sameInstance"), "true");
+        assert_eq!(global(&interpreter, "otherInstance"), "true");
+        assert_eq!(global(&interpreter, "sameFunction"), "true");
+        assert_eq!(global(&interpreter, "not"), "true");
+    }
+
+    #[test]
+    fn test_operand_errors() {
+        let (_, result) = run("1 < \"2\";");
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err,
+            LoxError::InvalidOperands {
+                lhs: "integer",
+                rhs: "string",
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Operator '<' can't be applied to an integer and a string"
+        );
+
+        let (_, result) = run("true + nil;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Operator '+' can't be applied to a boolean and a nil"
+        );
+
+        let (_, result) = run("-\"a\";");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Operator '-' can't be applied to a string"
+        );
+
+        let (_, result) = run("var a = 1; a();");
+        assert_eq!(result.unwrap_err().to_string(), "Can't call an integer");
     }
 }
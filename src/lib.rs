@@ -1,33 +1,80 @@
+use std::path::Path;
+
 use interpreter::Interpreter;
 use loxerror::LoxError;
+use resolver::Resolver;
 use statement_parser::StatementParser;
 use string_indexer::StringIndexer;
 use tokenizer::Lexer;
 
+mod bigint;
+mod class;
+mod environment;
 mod expr;
 mod expr_parser;
 mod function;
 mod interpreter;
 mod loxerror;
+mod map;
+mod module;
+mod native;
 mod object;
+mod resolver;
 mod statement;
 mod statement_parser;
 mod string_indexer;
 mod token;
 mod tokenizer;
-mod environment;
 
 pub fn run(source: &str) -> Result<(), LoxError> {
+    run_script(source, None)
+}
+
+/// Runs a script read from `path`, so its imports resolve relative to it
+pub fn run_file(path: &Path, source: &str) -> Result<(), LoxError> {
+    run_script(source, Some(path))
+}
+
+fn run_script(source: &str, path: Option<&Path>) -> Result<(), LoxError> {
     let mut lexer = Lexer::new();
 
     let mut string_indexer = StringIndexer::new(source);
-    if let Ok(mut token_store) = lexer.tokenize(&mut string_indexer, source) {
-        let mut statement_parser = StatementParser::new();
-        let statements = statement_parser.run(&mut token_store, &string_indexer)?;
+    let mut token_store = lexer
+        .tokenize(&mut string_indexer, source)
+        .map_err(|error| LoxError::InvalidSource { error })?;
 
-        let mut interpreter = Interpreter::new();
-        let _ = interpreter.run(&statements);
+    let mut statement_parser = StatementParser::new();
+    let statements = statement_parser.run(&mut token_store, &string_indexer)?;
+
+    let mut resolver = Resolver::new();
+    resolver.resolve(&statements)?;
+
+    let mut interpreter = Interpreter::new();
+    if let Some(path) = path {
+        interpreter.set_script(path);
     }
+    interpreter.run(&statements)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invalid_source() {
+        let result = run("var a = 1;\nvar s = \"${a\";");
+        assert!(matches!(
+            result,
+            Err(LoxError::InvalidSource { error }) if error == "Unterminated string at line: 2"
+        ));
+        let result = run("print \"${1\n");
+        assert!(matches!(
+            result,
+            Err(LoxError::InvalidSource { error })
+                if error == "Unterminated string interpolation at line: 2"
+        ));
+        assert!(run("print 1;").is_ok());
+    }
+}
@@ -1,9 +1,229 @@
+use std::fmt::Display;
+
+use crate::object::Object;
+
 #[derive(Debug)]
 pub enum LoxError {
-    InvalidToken { error: &'static str },
-    InvalidStatement { error: String },
+    /// Source the lexer couldn't split into tokens
+    InvalidSource {
+        error: String,
+    },
+    InvalidToken {
+        error: &'static str,
+    },
+    InvalidStatement {
+        error: String,
+    },
+    InvalidResolution {
+        error: String,
+    },
     UnexpectedEof,
-    InterpreterExpression,
-    InterpreterStatement,
+    InvalidOperand {
+        operator: String,
+        operand: &'static str,
+    },
+    InvalidOperands {
+        operator: String,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    NotCallable {
+        callee: &'static str,
+    },
+    UndefinedVariable {
+        name: String,
+    },
+    UndefinedProperty {
+        name: String,
+    },
+    InvalidPropertyAccess {
+        name: String,
+    },
+    InvalidSuperclass {
+        name: String,
+    },
+    InvalidTrait {
+        name: String,
+    },
+    /// `super` in a trait member included into a class with no superclass
+    NoSuperclass,
+    /// Two included traits offer a member the class doesn't declare itself
+    AmbiguousTraitMember {
+        class: String,
+        name: String,
+        first: String,
+        second: String,
+    },
+    InvalidArgument {
+        name: &'static str,
+        error: String,
+    },
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
     StringIndexOutOfBouds,
+    NotIndexable {
+        target: &'static str,
+    },
+    InvalidIndex {
+        index: String,
+    },
+    IndexOutOfBounds {
+        index: usize,
+        length: usize,
+    },
+    InvalidMapKey {
+        key: &'static str,
+    },
+    MissingKey {
+        key: String,
+    },
+    ImportFailed {
+        path: String,
+        error: String,
+    },
+    CircularImport {
+        path: String,
+    },
+    NotAnInteger {
+        operator: String,
+        value: String,
+    },
+    InvalidShift {
+        amount: i64,
+    },
+    /// A big integer result past the size the interpreter is willing to hold
+    ResultTooLarge {
+        operator: String,
+        bits: u64,
+    },
+    DivisionByZero {
+        operator: String,
+    },
+    /// Calls nested deeper than the interpreter allows, usually runaway recursion
+    StackOverflow {
+        depth: usize,
+    },
+    /// A value thrown by a script that no `catch` handled
+    Thrown {
+        value: ThrownValue,
+    },
+}
+
+/// Prefixes a type name with the indefinite article that reads right for it
+pub(crate) fn with_article(type_name: &str) -> String {
+    match type_name.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u') => format!("an {}", type_name),
+        _ => format!("a {}", type_name),
+    }
+}
+
+/// Wraps a thrown script value so it can travel inside a `LoxError`
+#[derive(Debug)]
+pub struct ThrownValue(pub(crate) Object);
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxError::InvalidToken { error } => write!(f, "{}", error),
+            LoxError::InvalidStatement { error } => write!(f, "{}", error),
+            LoxError::InvalidResolution { error } => write!(f, "{}", error),
+            LoxError::InvalidSource { error } => write!(f, "{}", error),
+            LoxError::UnexpectedEof => write!(f, "Unexpected end of file"),
+            LoxError::InvalidOperand { operator, operand } => {
+                write!(
+                    f,
+                    "Operator '{}' can't be applied to {}",
+                    operator,
+                    with_article(operand)
+                )
+            }
+            LoxError::InvalidOperands { operator, lhs, rhs } => write!(
+                f,
+                "Operator '{}' can't be applied to {} and {}",
+                operator,
+                with_article(lhs),
+                with_article(rhs)
+            ),
+            LoxError::NotCallable { callee } => write!(f, "Can't call {}", with_article(callee)),
+            LoxError::UndefinedVariable { name } => write!(f, "Undefined variable '{}'", name),
+            LoxError::UndefinedProperty { name } => write!(f, "Undefined property '{}'", name),
+            LoxError::InvalidPropertyAccess { name } => {
+                write!(f, "Only instances have properties, accessing '{}'", name)
+            }
+            LoxError::InvalidSuperclass { name } => {
+                write!(f, "Superclass '{}' must be a class", name)
+            }
+            LoxError::InvalidTrait { name } => write!(f, "Included '{}' must be a trait", name),
+            LoxError::NoSuperclass => {
+                write!(f, "Can't use 'super' in a class with no superclass")
+            }
+            LoxError::AmbiguousTraitMember {
+                class,
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Class '{}' gets '{}' from both trait '{}' and trait '{}', declare it in the class to choose",
+                class, name, first, second
+            ),
+            LoxError::InvalidArgument { name, error } => write!(f, "{}: {}", name, error),
+            LoxError::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} expected {} arguments but got {}",
+                name, expected, found
+            ),
+            LoxError::StringIndexOutOfBouds => write!(f, "String index out of bounds"),
+            LoxError::NotIndexable { target } => write!(f, "Can't index {}", with_article(target)),
+            LoxError::InvalidIndex { index } => {
+                write!(
+                    f,
+                    "List index must be a non-negative integer, found {}",
+                    index
+                )
+            }
+            LoxError::IndexOutOfBounds { index, length } => write!(
+                f,
+                "Index {} out of bounds for a list of length {}",
+                index, length
+            ),
+            LoxError::InvalidMapKey { key } => {
+                write!(f, "Can't use {} as a map key", with_article(key))
+            }
+            LoxError::MissingKey { key } => write!(f, "Key '{}' not found in map", key),
+            LoxError::ImportFailed { path, error } => {
+                write!(f, "Unable to import '{}': {}", path, error)
+            }
+            LoxError::CircularImport { path } => {
+                write!(f, "Circular import of '{}'", path)
+            }
+            LoxError::NotAnInteger { operator, value } => write!(
+                f,
+                "Operator '{}' needs integers within 64-bit range, found {}",
+                operator, value
+            ),
+            LoxError::InvalidShift { amount } => {
+                write!(f, "Shift amount must be between 0 and 63, found {}", amount)
+            }
+            LoxError::ResultTooLarge { operator, bits } => write!(
+                f,
+                "Result of '{}' is too large, integers are limited to {} bits",
+                operator, bits
+            ),
+            LoxError::DivisionByZero { operator } => {
+                write!(f, "Integer division by zero in '{}'", operator)
+            }
+            LoxError::StackOverflow { depth } => {
+                write!(f, "Stack overflow, calls nested more than {} deep", depth)
+            }
+            LoxError::Thrown { value } => write!(f, "Uncaught exception: {}", value.0),
+        }
+    }
 }
@@ -2,6 +2,7 @@ use core::panic;
 use std::{
     fs::File,
     io::{self, BufRead, BufReader, Read},
+    path::Path,
 };
 
 use clap::Parser;
@@ -32,7 +33,11 @@ fn run_file(file: &str) {
             let mut content = String::new();
 
             match buffer.read_to_string(&mut content) {
-                Ok(_) => run(&content),
+                Ok(_) => {
+                    if let Err(err) = loxrs::run_file(Path::new(file), &content) {
+                        println!("Error: {}", err);
+                    }
+                }
                 Err(err) => panic!(
                     "unable to read in the content of the script: {}, {}",
                     file, err
@@ -44,7 +49,9 @@ fn run_file(file: &str) {
 }
 
 fn run(source: &str) {
-    let _ = loxrs::run(source);
+    if let Err(err) = loxrs::run(source) {
+        println!("Error: {}", err);
+    }
 }
 
 fn setup_logging() {
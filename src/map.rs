@@ -0,0 +1,179 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{bigint::BigInt, loxerror::LoxError, object::Object};
+
+/// The hashable subset of values that can be used as map keys. Floats hash by
+/// their bit pattern, except whole ones which fold into the integer they equal
+/// so `1` and `1.0` find the same entry, as do `-0.0` and `0`. Integers follow
+/// `Object` in only using `BigInteger` outside the range of an `i64`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum MapKey {
+    Boolean(bool),
+    Null,
+    Integer(i64),
+    BigInteger(BigInt),
+    Number(u64),
+    String(String),
+}
+
+impl MapKey {
+    pub(crate) fn to_object(&self) -> Object {
+        match self {
+            MapKey::Boolean(value) => Object::Boolean(*value),
+            MapKey::Null => Object::Null,
+            MapKey::Integer(value) => Object::Integer(*value),
+            MapKey::BigInteger(value) => Object::BigInteger(value.clone()),
+            MapKey::Number(bits) => Object::Number(f64::from_bits(*bits)),
+            MapKey::String(value) => Object::String(value.clone()),
+        }
+    }
+}
+
+impl TryFrom<&Object> for MapKey {
+    type Error = LoxError;
+
+    fn try_from(value: &Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Boolean(value) => Ok(MapKey::Boolean(*value)),
+            Object::Null => Ok(MapKey::Null),
+            Object::Integer(value) => Ok(MapKey::Integer(*value)),
+            Object::BigInteger(value) => Ok(MapKey::BigInteger(value.clone())),
+            Object::Number(value) => match BigInt::from_f64(*value) {
+                Some(whole) => match whole.to_i64() {
+                    Some(integer) => Ok(MapKey::Integer(integer)),
+                    None => Ok(MapKey::BigInteger(whole)),
+                },
+                None => Ok(MapKey::Number(value.to_bits())),
+            },
+            Object::String(value) => Ok(MapKey::String(value.clone())),
+            other => Err(LoxError::InvalidMapKey {
+                key: other.type_name(),
+            }),
+        }
+    }
+}
+
+/// A map remembering the order its keys were first inserted in, so printing
+/// and iterating it is predictable
+#[derive(Debug, Default)]
+pub(crate) struct Map {
+    entries: Vec<(MapKey, Object)>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn get(&self, key: &MapKey) -> Option<&Object> {
+        self.indices.get(key).map(|index| &self.entries[*index].1)
+    }
+
+    pub(crate) fn contains(&self, key: &MapKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub(crate) fn insert(&mut self, key: MapKey, value: Object) {
+        match self.indices.get(&key) {
+            Some(index) => self.entries[*index].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &MapKey) -> Option<Object> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+
+        // Entries after the removed one have shifted down by one
+        for position in self.indices.values_mut() {
+            if *position > index {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &MapKey> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    /// Writes the entries, passing `printing` on so values that lead back to
+    /// a map or list being printed are cut short, see `Object::write`
+    pub(crate) fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut HashSet<*const ()>,
+    ) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for (index, (key, value)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: ", key.to_object())?;
+            value.write(f, printing)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut HashSet::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(value: Object) -> MapKey {
+        MapKey::try_from(&value).unwrap()
+    }
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(key(Object::Integer(0)), key(Object::Number(-0.0)));
+        assert_eq!(key(Object::Integer(3)), key(Object::Number(3.0)));
+        assert_ne!(key(Object::Number(0.5)), key(Object::Integer(0)));
+        let big = BigInt::parse("100000000000000000000").unwrap();
+        assert_eq!(key(Object::BigInteger(big)), key(Object::Number(1e20)));
+        assert_ne!(
+            key(Object::Integer(1)),
+            key(Object::String("1".to_string()))
+        );
+        assert_eq!(key(Object::Null).to_object(), Object::Null);
+        assert!(matches!(
+            MapKey::try_from(&Object::List(Default::default())),
+            Err(LoxError::InvalidMapKey { key: "list" })
+        ));
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = Map::new();
+        map.insert(key(Object::String("b".to_string())), Object::Integer(1));
+        map.insert(key(Object::String("a".to_string())), Object::Integer(2));
+        map.insert(key(Object::Boolean(true)), Object::Null);
+        map.insert(key(Object::String("b".to_string())), Object::Integer(3));
+        assert_eq!(map.to_string(), "{b: 3, a: 2, true: null}");
+
+        assert_eq!(
+            map.remove(&key(Object::String("b".to_string()))),
+            Some(Object::Integer(3))
+        );
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&key(Object::Boolean(true))), Some(&Object::Null));
+        assert_eq!(map.to_string(), "{a: 2, true: null}");
+    }
+}
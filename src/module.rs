@@ -0,0 +1,61 @@
+use std::{cell::RefCell, fmt::Display, fs, path::Path, rc::Rc};
+
+use crate::{
+    environment::Environment, loxerror::LoxError, object::Object, resolver::Resolver,
+    statement::Statement, statement_parser::StatementParser, string_indexer::StringIndexer,
+    tokenizer::Lexer,
+};
+
+/// The namespace an imported file is bound to, exposing the definitions made
+/// at the top level of that file
+#[derive(Debug)]
+pub(crate) struct Module {
+    pub(crate) name: String,
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    pub(crate) fn new(name: String, globals: Rc<RefCell<Environment>>) -> Self {
+        Self { name, globals }
+    }
+
+    /// Looks up a top-level definition of the module, ignoring the built-ins
+    /// its globals are layered over
+    pub(crate) fn get(&self, name: &str) -> Result<Object, LoxError> {
+        self.globals
+            .borrow()
+            .get_at(0, name)
+            .map_err(|_| LoxError::UndefinedProperty {
+                name: name.to_string(),
+            })
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+/// Reads, parses and resolves the file at `path`, ready to be run
+pub(crate) fn load(path: &Path) -> Result<Vec<Statement>, LoxError> {
+    let import_failed = |error: String| LoxError::ImportFailed {
+        path: path.display().to_string(),
+        error,
+    };
+
+    let source = fs::read_to_string(path).map_err(|err| import_failed(err.to_string()))?;
+
+    let mut string_indexer = StringIndexer::new(&source);
+    let mut token_store = Lexer::new()
+        .tokenize(&mut string_indexer, &source)
+        .map_err(import_failed)?;
+    let statements = StatementParser::new()
+        .run(&mut token_store, &string_indexer)
+        .map_err(|err| import_failed(err.to_string()))?;
+    Resolver::new()
+        .resolve(&statements)
+        .map_err(|err| import_failed(err.to_string()))?;
+
+    Ok(statements)
+}
@@ -0,0 +1,264 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    bigint::BigInt,
+    interpreter::Interpreter,
+    loxerror::{with_article, LoxError},
+    map::MapKey,
+    object::Object,
+};
+
+/// Registers every built-in function with the interpreter
+pub(crate) fn register(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+    interpreter.define_native("str", 1, str);
+    interpreter.define_native("num", 1, num);
+    interpreter.define_native("type", 1, type_of);
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("push", 2, push);
+    interpreter.define_native("pop", 1, pop);
+    interpreter.define_native("has", 2, has);
+    interpreter.define_native("remove", 2, remove);
+    interpreter.define_native("keys", 1, keys);
+}
+
+/// Seconds since the unix epoch, for timing scripts
+fn clock(_interpreter: &mut Interpreter, _args: &[Object]) -> Result<Object, LoxError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| LoxError::InvalidArgument {
+            name: "clock",
+            error: err.to_string(),
+        })?;
+    Ok(Object::Number(now.as_secs_f64()))
+}
+
+fn str(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    Ok(Object::String(args[0].to_string()))
+}
+
+fn num(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::Integer(_) | Object::BigInteger(_) | Object::Number(_) => Ok(args[0].clone()),
+        Object::String(value) => {
+            let value = value.trim();
+            if let Ok(integer) = value.parse() {
+                return Ok(Object::Integer(integer));
+            }
+            if let Some(integer) = BigInt::parse(value) {
+                return Ok(Object::from(integer));
+            }
+            match value.parse() {
+                Ok(value) => Ok(Object::Number(value)),
+                Err(_) => Err(LoxError::InvalidArgument {
+                    name: "num",
+                    error: format!("unable to parse '{}' as a number", value),
+                }),
+            }
+        }
+        other => Err(LoxError::InvalidArgument {
+            name: "num",
+            error: format!(
+                "can't convert {} to a number",
+                with_article(other.type_name())
+            ),
+        }),
+    }
+}
+
+fn type_of(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    Ok(Object::String(args[0].type_name().to_string()))
+}
+
+/// The number of elements in a list or map, or characters in a string
+fn len(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::List(elements) => Ok(Object::Integer(elements.borrow().len() as i64)),
+        Object::Map(map) => Ok(Object::Integer(map.borrow().len() as i64)),
+        Object::String(value) => Ok(Object::Integer(value.chars().count() as i64)),
+        other => Err(LoxError::InvalidArgument {
+            name: "len",
+            error: format!("{} has no length", with_article(other.type_name())),
+        }),
+    }
+}
+
+/// Appends a value to the end of a list
+fn push(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::List(elements) => {
+            elements.borrow_mut().push(args[1].clone());
+            Ok(Object::Null)
+        }
+        other => Err(LoxError::InvalidArgument {
+            name: "push",
+            error: format!("can't push onto {}", with_article(other.type_name())),
+        }),
+    }
+}
+
+/// Removes and returns the last value of a list
+fn pop(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::List(elements) => {
+            elements
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| LoxError::InvalidArgument {
+                    name: "pop",
+                    error: "can't pop from an empty list".to_string(),
+                })
+        }
+        other => Err(LoxError::InvalidArgument {
+            name: "pop",
+            error: format!("can't pop from {}", with_article(other.type_name())),
+        }),
+    }
+}
+
+/// Whether a map holds an entry for the key
+fn has(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::Map(map) => Ok(Object::Boolean(
+            map.borrow().contains(&MapKey::try_from(&args[1])?),
+        )),
+        other => Err(LoxError::InvalidArgument {
+            name: "has",
+            error: format!("can't look up keys in {}", with_article(other.type_name())),
+        }),
+    }
+}
+
+/// Removes the entry for the key from a map, returning its value or nil when
+/// there was none
+fn remove(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::Map(map) => Ok(map
+            .borrow_mut()
+            .remove(&MapKey::try_from(&args[1])?)
+            .unwrap_or(Object::Null)),
+        other => Err(LoxError::InvalidArgument {
+            name: "remove",
+            error: format!("can't remove keys from {}", with_article(other.type_name())),
+        }),
+    }
+}
+
+/// The keys of a map as a new list, in insertion order
+fn keys(_interpreter: &mut Interpreter, args: &[Object]) -> Result<Object, LoxError> {
+    match &args[0] {
+        Object::Map(map) => {
+            let keys = map.borrow().keys().map(MapKey::to_object).collect();
+            Ok(Object::List(Rc::new(RefCell::new(keys))))
+        }
+        other => Err(LoxError::InvalidArgument {
+            name: "keys",
+            error: format!("{} has no keys", with_article(other.type_name())),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        resolver::Resolver, statement_parser::StatementParser, string_indexer::StringIndexer,
+        tokenizer::Lexer,
+    };
+
+    use super::*;
+
+    fn eval(source: &str) -> Result<Object, LoxError> {
+        let source = format!("var result = {};", source);
+        let mut lexer = Lexer::new();
+        let mut string_indexer = StringIndexer::new(&source);
+        let mut token_store = lexer.tokenize(&mut string_indexer, &source).unwrap();
+        let statements = StatementParser::new()
+            .run(&mut token_store, &string_indexer)
+            .unwrap();
+        Resolver::new().resolve(&statements).unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.run(&statements)?;
+        interpreter.global("result")
+    }
+
+    #[test]
+    fn test_clock() {
+        assert!(matches!(eval("clock()"), Ok(Object::Number(value)) if value > 0.0));
+        assert!(matches!(
+            eval("clock(1)"),
+            Err(LoxError::ArityMismatch { .. })
+        ));
+        assert_eq!(eval("clock").unwrap().to_string(), "<native fn clock>");
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(eval("str(1.5) + \"!\"").unwrap().to_string(), "1.5!");
+        assert_eq!(eval("str(nil)").unwrap().to_string(), "null");
+        assert_eq!(eval("num(\" 42 \") + 1").unwrap().to_string(), "43");
+        assert!(matches!(
+            eval("num(\"abc\")"),
+            Err(LoxError::InvalidArgument { name: "num", .. })
+        ));
+        assert!(matches!(
+            eval("num(true)"),
+            Err(LoxError::InvalidArgument { name: "num", .. })
+        ));
+    }
+
+    #[test]
+    fn test_type() {
+        assert_eq!(eval("type(1)").unwrap().to_string(), "integer");
+        assert_eq!(eval("type(1.5)").unwrap().to_string(), "number");
+        assert_eq!(eval("type(\"a\")").unwrap().to_string(), "string");
+        assert_eq!(eval("type(type)").unwrap().to_string(), "function");
+        assert_eq!(eval("type(nil)").unwrap().to_string(), "nil");
+        assert_eq!(eval("type([])").unwrap().to_string(), "list");
+        assert_eq!(eval("type({})").unwrap().to_string(), "map");
+    }
+
+    #[test]
+    fn test_list_builtins() {
+        assert_eq!(eval("len([1, 2, 3])").unwrap().to_string(), "3");
+        assert_eq!(eval("len(\"héllo\")").unwrap().to_string(), "5");
+        assert_eq!(eval("pop([1, 2])").unwrap().to_string(), "2");
+        assert!(matches!(
+            eval("pop([])"),
+            Err(LoxError::InvalidArgument { name: "pop", .. })
+        ));
+        assert!(matches!(
+            eval("push(1, 2)"),
+            Err(LoxError::InvalidArgument { name: "push", .. })
+        ));
+        assert!(matches!(
+            eval("len(nil)"),
+            Err(LoxError::InvalidArgument { name: "len", .. })
+        ));
+    }
+
+    #[test]
+    fn test_map_builtins() {
+        assert_eq!(eval("len({1: 2, 3: 4})").unwrap().to_string(), "2");
+        assert_eq!(eval("has({nil: 1}, nil)").unwrap().to_string(), "true");
+        assert_eq!(eval("has({true: 1}, false)").unwrap().to_string(), "false");
+        assert_eq!(eval("remove({\"a\": 1}, \"a\")").unwrap().to_string(), "1");
+        assert_eq!(eval("remove({}, \"a\")").unwrap().to_string(), "null");
+        assert_eq!(
+            eval("keys({\"b\": 1, \"a\": 2, 0: 3})")
+                .unwrap()
+                .to_string(),
+            "[b, a, 0]"
+        );
+        assert!(matches!(
+            eval("has({}, [])"),
+            Err(LoxError::InvalidMapKey { key: "list" })
+        ));
+        assert!(matches!(
+            eval("keys([])"),
+            Err(LoxError::InvalidArgument { name: "keys", .. })
+        ));
+    }
+}
@@ -1,23 +1,146 @@
-use crate::function::Function;
+use std::{cell::RefCell, collections::HashSet, fmt::Display, rc::Rc};
 
-#[derive(Debug)]
+use crate::{
+    bigint::BigInt,
+    class::{Class, Instance, Trait},
+    function::Function,
+    map::Map,
+    module::Module,
+};
+
+#[derive(Debug, Clone)]
 pub(crate) enum Object {
     Boolean(bool),
     Null,
+    Integer(i64),
+    /// An integer outside the range of an `i64`. Results that fit are always
+    /// narrowed back to `Integer`, so the two never hold the same value
+    BigInteger(BigInt),
     Number(f64),
     String(String),
 
-    Callable(Function),
+    Callable(Rc<Function>),
+    Class(Rc<Class>),
+    Trait(Rc<Trait>),
+    Instance(Rc<RefCell<Instance>>),
+    List(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<Map>>),
+    Module(Rc<Module>),
+}
+
+impl Object {
+    /// The name of the value's type, as surfaced in runtime errors
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Object::Boolean(_) => "boolean",
+            Object::Null => "nil",
+            Object::Integer(_) | Object::BigInteger(_) => "integer",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Callable(_) => "function",
+            Object::Class(_) => "class",
+            Object::Trait(_) => "trait",
+            Object::Instance(_) => "instance",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Module(_) => "module",
+        }
+    }
+}
+
+/// Values of different types are never equal, apart from integers and floats
+/// holding exactly the same number, which agrees with how map keys match.
+/// Functions, classes, instances, lists, maps and modules compare by identity
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Boolean(l), Object::Boolean(r)) => l == r,
+            (Object::Null, Object::Null) => true,
+            (Object::Integer(l), Object::Integer(r)) => l == r,
+            (Object::Number(l), Object::Number(r)) => l == r,
+            (Object::BigInteger(l), Object::BigInteger(r)) => l == r,
+            (Object::Integer(l), Object::Number(r)) | (Object::Number(r), Object::Integer(l)) => {
+                BigInt::from_f64(*r).and_then(|r| r.to_i64()) == Some(*l)
+            }
+            (Object::BigInteger(l), Object::Number(r))
+            | (Object::Number(r), Object::BigInteger(l)) => {
+                BigInt::from_f64(*r).is_some_and(|r| r == *l)
+            }
+            (Object::String(l), Object::String(r)) => l == r,
+            (Object::Callable(l), Object::Callable(r)) => Rc::ptr_eq(l, r),
+            (Object::Class(l), Object::Class(r)) => Rc::ptr_eq(l, r),
+            (Object::Trait(l), Object::Trait(r)) => Rc::ptr_eq(l, r),
+            (Object::Instance(l), Object::Instance(r)) => Rc::ptr_eq(l, r),
+            (Object::List(l), Object::List(r)) => Rc::ptr_eq(l, r),
+            (Object::Map(l), Object::Map(r)) => Rc::ptr_eq(l, r),
+            (Object::Module(l), Object::Module(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<BigInt> for Object {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Object::Integer(value),
+            None => Object::BigInteger(value),
+        }
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &mut HashSet::new())
+    }
 }
 
-impl<'a> ToString for Object {
-    fn to_string(&self) -> String {
+impl Object {
+    /// Writes the value, tracking the lists and maps already being printed so
+    /// one that contains itself prints as `[...]` or `{...}` when it comes
+    /// round again rather than recursing forever
+    pub(crate) fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        printing: &mut HashSet<*const ()>,
+    ) -> std::fmt::Result {
         match self {
-            Object::Boolean(value) => value.to_string(),
-            Object::Null => "null".to_string(),
-            Object::Number(value) => value.to_string(),
-            Object::String(value) => value.to_string(),
-            Object::Callable(_function) => todo!(),
+            Object::Boolean(value) => write!(f, "{}", value),
+            Object::Null => write!(f, "null"),
+            Object::Integer(value) => write!(f, "{}", value),
+            Object::BigInteger(value) => write!(f, "{}", value),
+            // Whole floats keep their decimal point to tell them from integers
+            Object::Number(value) if value.fract() == 0.0 => write!(f, "{:.1}", value),
+            Object::Number(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
+            Object::Callable(function) => write!(f, "{}", function),
+            Object::Class(class) => write!(f, "{}", class),
+            Object::Trait(included) => write!(f, "{}", included),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
+            Object::List(elements) => {
+                let id = Rc::as_ptr(elements) as *const ();
+                if !printing.insert(id) {
+                    return write!(f, "[...]");
+                }
+                write!(f, "[")?;
+                for (index, element) in elements.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write(f, printing)?;
+                }
+                printing.remove(&id);
+                write!(f, "]")
+            }
+            Object::Map(map) => {
+                let id = Rc::as_ptr(map) as *const ();
+                if !printing.insert(id) {
+                    return write!(f, "{{...}}");
+                }
+                map.borrow().write(f, printing)?;
+                printing.remove(&id);
+                Ok(())
+            }
+            Object::Module(module) => write!(f, "{}", module),
         }
     }
 }
@@ -0,0 +1,670 @@
+use std::{collections::HashMap, rc::Rc};
+
+use tracing::trace;
+
+use crate::{
+    expr::{self, ChainLink, Depth, Expr, Literal, Operator},
+    loxerror::LoxError,
+    statement::{
+        self, CatchClause, ClassDeclaration, FunctionDeclaration, Statement, TraitDeclaration,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+    Trait,
+}
+
+/// Walks the parsed statements ahead of interpretation, recording how many
+/// scopes separate each variable reference from its declaration
+pub(crate) struct Resolver {
+    /// Local scopes, mapping a name to whether its initializer has completed.
+    /// Globals are not tracked
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+}
+
+impl Resolver {
+    pub(crate) fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, statements: &[Statement]) -> Result<(), LoxError> {
+        statements
+            .iter()
+            .try_for_each(|statement| statement.accept(self))
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) -> Result<(), LoxError> {
+        expr.accept(self)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str) -> Result<(), LoxError> {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.contains_key(name) {
+                return Err(LoxError::InvalidResolution {
+                    error: format!("Already a variable named '{}' in this scope", name),
+                });
+            }
+            scope.insert(name.to_string(), false);
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    fn resolve_local(&self, name: &str, depth: &Depth) {
+        if let Some(distance) = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+        {
+            trace!("resolved '{}' at depth {}", name, distance);
+            depth.set(distance);
+        }
+    }
+
+    fn resolve_function(
+        &mut self,
+        declaration: &FunctionDeclaration,
+        function_type: FunctionType,
+    ) -> Result<(), LoxError> {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        let result = declaration
+            .args
+            .iter()
+            .try_for_each(|arg| {
+                self.declare(arg)?;
+                self.define(arg);
+                Ok(())
+            })
+            .and_then(|_| self.resolve(&declaration.body));
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        result
+    }
+}
+
+impl expr::Visitor<()> for Resolver {
+    fn visit_binary_expression(
+        &mut self,
+        lhs: &Expr,
+        _operator: &Operator,
+        rhs: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(lhs)?;
+        self.resolve_expr(rhs)
+    }
+
+    fn visit_literal_expression(&self, _literal: &Literal) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_unary_expression(
+        &mut self,
+        _operator: &Operator,
+        expr: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(expr)
+    }
+
+    fn visit_grouping_expression(&mut self, expr: &Expr) -> Result<(), LoxError> {
+        self.resolve_expr(expr)
+    }
+
+    fn visit_variable_expression(&mut self, name: &str, depth: &Depth) -> Result<(), LoxError> {
+        if let Some(false) = self.scopes.last().and_then(|scope| scope.get(name)) {
+            return Err(LoxError::InvalidResolution {
+                error: format!(
+                    "Can't read local variable '{}' in its own initializer",
+                    name
+                ),
+            });
+        }
+
+        self.resolve_local(name, depth);
+        Ok(())
+    }
+
+    fn visit_assign_expression(
+        &mut self,
+        name: &str,
+        value: &Expr,
+        depth: &Depth,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(value)?;
+        self.resolve_local(name, depth);
+        Ok(())
+    }
+
+    fn visit_function_expression(&mut self, callee: &Expr, args: &[Expr]) -> Result<(), LoxError> {
+        self.resolve_expr(callee)?;
+        args.iter().try_for_each(|arg| self.resolve_expr(arg))
+    }
+
+    fn visit_get_expression(&mut self, object: &Expr, _name: &str) -> Result<(), LoxError> {
+        self.resolve_expr(object)
+    }
+
+    fn visit_optional_chain_expression(
+        &mut self,
+        object: &Expr,
+        links: &[ChainLink],
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(object)?;
+        links.iter().try_for_each(|link| match link {
+            ChainLink::Get { .. } => Ok(()),
+            ChainLink::Call(args) => args.iter().try_for_each(|arg| self.resolve_expr(arg)),
+            ChainLink::Index(index) => self.resolve_expr(index),
+        })
+    }
+
+    fn visit_compound_assign_expression(
+        &mut self,
+        target: &Expr,
+        _operator: &Operator,
+        value: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(value)?;
+        self.resolve_expr(target)
+    }
+
+    fn visit_increment_expression(
+        &mut self,
+        target: &Expr,
+        _operator: &Operator,
+        _prefix: bool,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(target)
+    }
+
+    fn visit_conditional_expression(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(condition)?;
+        self.resolve_expr(then_branch)?;
+        self.resolve_expr(else_branch)
+    }
+
+    fn visit_list_expression(&mut self, elements: &[Expr]) -> Result<(), LoxError> {
+        elements
+            .iter()
+            .try_for_each(|element| self.resolve_expr(element))
+    }
+
+    fn visit_lambda_expression(
+        &mut self,
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<(), LoxError> {
+        self.resolve_function(declaration, FunctionType::Function)
+    }
+
+    fn visit_interpolation_expression(&mut self, parts: &[Expr]) -> Result<(), LoxError> {
+        parts.iter().try_for_each(|part| self.resolve_expr(part))
+    }
+
+    fn visit_map_expression(&mut self, entries: &[(Expr, Expr)]) -> Result<(), LoxError> {
+        entries.iter().try_for_each(|(key, value)| {
+            self.resolve_expr(key)?;
+            self.resolve_expr(value)
+        })
+    }
+
+    fn visit_index_expression(&mut self, target: &Expr, index: &Expr) -> Result<(), LoxError> {
+        self.resolve_expr(target)?;
+        self.resolve_expr(index)
+    }
+
+    fn visit_set_index_expression(
+        &mut self,
+        target: &Expr,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(value)?;
+        self.resolve_expr(target)?;
+        self.resolve_expr(index)
+    }
+
+    fn visit_set_expression(
+        &mut self,
+        object: &Expr,
+        _name: &str,
+        value: &Expr,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(value)?;
+        self.resolve_expr(object)
+    }
+
+    fn visit_super_expression(&mut self, _method: &str, depth: &Depth) -> Result<(), LoxError> {
+        match self.current_class {
+            ClassType::None => Err(LoxError::InvalidResolution {
+                error: "Can't use 'super' outside of a class".to_string(),
+            }),
+            ClassType::Class => Err(LoxError::InvalidResolution {
+                error: "Can't use 'super' in a class with no superclass".to_string(),
+            }),
+            // Each class including a trait binds `super` for its members,
+            // so whether there is a superclass is only known at runtime
+            ClassType::Subclass | ClassType::Trait => {
+                self.resolve_local("super", depth);
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_this_expression(&mut self, depth: &Depth) -> Result<(), LoxError> {
+        if self.current_class == ClassType::None {
+            return Err(LoxError::InvalidResolution {
+                error: "Can't use 'this' outside of a class".to_string(),
+            });
+        }
+
+        self.resolve_local("this", depth);
+        Ok(())
+    }
+}
+
+impl statement::Visitor<()> for Resolver {
+    fn visit_print_statement(&mut self, expr: &Expr) -> Result<(), LoxError> {
+        self.resolve_expr(expr)
+    }
+
+    fn visit_if_statement(
+        &mut self,
+        condition: &Expr,
+        if_branch: &Statement,
+        else_branch: Option<&Statement>,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(condition)?;
+        if_branch.accept(self)?;
+        if let Some(other) = else_branch {
+            other.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_while_statement(
+        &mut self,
+        condition: &Expr,
+        body: &Statement,
+        increment: Option<&Expr>,
+        _label: Option<&str>,
+    ) -> Result<(), LoxError> {
+        self.resolve_expr(condition)?;
+        body.accept(self)?;
+        if let Some(increment) = increment {
+            self.resolve_expr(increment)?;
+        }
+        Ok(())
+    }
+
+    fn visit_import_statement(&mut self, _path: &str, name: &str) -> Result<(), LoxError> {
+        self.declare(name)?;
+        self.define(name);
+        Ok(())
+    }
+
+    fn visit_throw_statement(&mut self, value: &Expr) -> Result<(), LoxError> {
+        self.resolve_expr(value)
+    }
+
+    fn visit_try_statement(
+        &mut self,
+        body: &[Statement],
+        catch: Option<&CatchClause>,
+        finally: Option<&[Statement]>,
+    ) -> Result<(), LoxError> {
+        self.visit_block_statement(body)?;
+
+        if let Some(catch) = catch {
+            // The caught value lives in the same scope as the handler's body
+            self.begin_scope();
+            let result = self.declare(&catch.name).and_then(|_| {
+                self.define(&catch.name);
+                self.resolve(&catch.body)
+            });
+            self.end_scope();
+            result?;
+        }
+
+        match finally {
+            Some(finally) => self.visit_block_statement(finally),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_break_statement(&mut self, _label: Option<&str>) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_continue_statement(&mut self, _label: Option<&str>) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_expression_statement(&mut self, expr: &Expr) -> Result<(), LoxError> {
+        self.resolve_expr(expr)
+    }
+
+    fn visit_block_statement(&mut self, statements: &[Statement]) -> Result<(), LoxError> {
+        self.begin_scope();
+        let result = self.resolve(statements);
+        self.end_scope();
+        result
+    }
+
+    fn visit_function_statement(
+        &mut self,
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<(), LoxError> {
+        self.declare(&declaration.name)?;
+        self.define(&declaration.name);
+        self.resolve_function(declaration, FunctionType::Function)
+    }
+
+    fn visit_class_statement(
+        &mut self,
+        declaration: &Rc<ClassDeclaration>,
+    ) -> Result<(), LoxError> {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(&declaration.name)?;
+        self.define(&declaration.name);
+
+        if let Some(superclass) = &declaration.superclass {
+            if let Expr::Variable(name, _) = superclass {
+                if name == &declaration.name {
+                    self.current_class = enclosing_class;
+                    return Err(LoxError::InvalidResolution {
+                        error: format!("A class can't inherit from itself: '{}'", name),
+                    });
+                }
+            }
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass)?;
+        }
+        for included in &declaration.traits {
+            self.resolve_expr(included)?;
+        }
+
+        if declaration.superclass.is_some() {
+            self.begin_scope();
+            self.define("super");
+        }
+
+        self.begin_scope();
+        self.define("this");
+        let result = declaration
+            .methods
+            .iter()
+            .try_for_each(|method| {
+                let function_type = if method.name == "init" {
+                    FunctionType::Initializer
+                } else {
+                    FunctionType::Method
+                };
+                self.resolve_function(method, function_type)
+            })
+            .and_then(|_| {
+                declaration
+                    .getters
+                    .iter()
+                    .chain(&declaration.class_methods)
+                    .try_for_each(|method| self.resolve_function(method, FunctionType::Method))
+            });
+        self.end_scope();
+
+        if declaration.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+        result?;
+
+        // Class fields are initialised outside of any method, once the class
+        // is defined
+        declaration
+            .fields
+            .iter()
+            .filter_map(|(_, initializer)| initializer.as_ref())
+            .try_for_each(|initializer| self.resolve_expr(initializer))
+    }
+
+    fn visit_trait_statement(
+        &mut self,
+        declaration: &Rc<TraitDeclaration>,
+    ) -> Result<(), LoxError> {
+        self.declare(&declaration.name)?;
+        self.define(&declaration.name);
+
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Trait;
+
+        // An `init` is an initializer of every class that includes it. The
+        // members always close over `super`, as bound by each including class
+        self.begin_scope();
+        self.define("super");
+        self.begin_scope();
+        self.define("this");
+        let result = declaration
+            .methods
+            .iter()
+            .try_for_each(|method| {
+                let function_type = if method.name == "init" {
+                    FunctionType::Initializer
+                } else {
+                    FunctionType::Method
+                };
+                self.resolve_function(method, function_type)
+            })
+            .and_then(|_| {
+                declaration
+                    .getters
+                    .iter()
+                    .try_for_each(|getter| self.resolve_function(getter, FunctionType::Method))
+            });
+        self.end_scope();
+        self.end_scope();
+
+        self.current_class = enclosing_class;
+        result
+    }
+
+    fn visit_var_statement(
+        &mut self,
+        name: &str,
+        initializer: Option<&Expr>,
+    ) -> Result<(), LoxError> {
+        self.declare(name)?;
+        if let Some(expr) = initializer {
+            self.resolve_expr(expr)?;
+        }
+        self.define(name);
+        Ok(())
+    }
+
+    fn visit_return_statement(&mut self, value: Option<&Expr>) -> Result<(), LoxError> {
+        if self.current_function == FunctionType::None {
+            return Err(LoxError::InvalidResolution {
+                error: "Can't return from top-level code".to_string(),
+            });
+        }
+
+        match value {
+            Some(_) if self.current_function == FunctionType::Initializer => {
+                Err(LoxError::InvalidResolution {
+                    error: "Can't return a value from an initializer".to_string(),
+                })
+            }
+            Some(expr) => self.resolve_expr(expr),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        statement_parser::StatementParser, string_indexer::StringIndexer, tokenizer::Lexer,
+    };
+
+    use super::*;
+
+    fn resolve(source: &str) -> Result<Vec<Statement>, LoxError> {
+        let mut lexer = Lexer::new();
+        let mut string_indexer = StringIndexer::new(source);
+        let mut token_store = lexer.tokenize(&mut string_indexer, source).unwrap();
+        let statements = StatementParser::new()
+            .run(&mut token_store, &string_indexer)
+            .unwrap();
+        Resolver::new().resolve(&statements)?;
+        Ok(statements)
+    }
+
+    #[test]
+    fn test_depths() {
+        let statements = resolve("var a; { var b; { a; b; } }").unwrap();
+        assert_eq!(
+            format!("{:?}", statements[1]),
+            "Block([Var { name: \"b\", initializer: None, line: 1 }, \
+             Block([Expression(Variable(\"a\", Depth(None)), 1), \
+             Expression(Variable(\"b\", Depth(Some(1))), 1)])])"
+        );
+
+        let statements = resolve("fun f(a) { { a = 1; } }").unwrap();
+        match &statements[0] {
+            Statement::Function(declaration) => assert_eq!(
+                format!("{:?}", declaration.body),
+                "[Block([Expression(Assign(\"a\", Literal(Integer(1)), Depth(Some(1))), 1)])]"
+            ),
+            statement => panic!("Expected a function, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_own_initializer() {
+        assert!(matches!(
+            resolve("{ var a = a; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(resolve("var a = a;").is_ok());
+    }
+
+    #[test]
+    fn test_redeclaration() {
+        assert!(matches!(
+            resolve("{ var a; var a; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(matches!(
+            resolve("fun f(a, a) {}"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(resolve("var a; var a;").is_ok());
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        assert!(matches!(
+            resolve("return 1;"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(matches!(
+            resolve("{ return; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(resolve("fun f() { { return; } }").is_ok());
+    }
+
+    #[test]
+    fn test_this() {
+        assert!(
+            resolve("class Foo { bar() { fun inner() { return this; } return inner; } }").is_ok()
+        );
+        assert!(matches!(
+            resolve("print this;"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(matches!(
+            resolve("fun f() { return this; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(
+            resolve("class Foo { class make() { return this; } size { return this; } }").is_ok()
+        );
+        assert!(matches!(
+            resolve("class Foo { class var me = this; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+    }
+
+    #[test]
+    fn test_initializer_return() {
+        assert!(resolve("class Foo { init() { return; } }").is_ok());
+        assert!(matches!(
+            resolve("class Foo { init() { return 1; } }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(resolve("trait T { init() { return; } }").is_ok());
+        assert!(matches!(
+            resolve("trait T { init() { return 5; } } class C with T {}"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+    }
+
+    #[test]
+    fn test_super() {
+        assert!(resolve("class A {} class B < A { test() { return super.test; } }").is_ok());
+        assert!(matches!(
+            resolve("class A < A {}"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(matches!(
+            resolve("class A { test() { return super.test; } }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(matches!(
+            resolve("fun f() { return super.test; }"),
+            Err(LoxError::InvalidResolution { .. })
+        ));
+        assert!(resolve("trait T { test() { return super.test; } }").is_ok());
+        assert!(resolve("trait T { me() { return this; } } class A with T {}").is_ok());
+    }
+}
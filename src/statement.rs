@@ -1,50 +1,193 @@
-use crate::{expr::Expr, loxerror::LoxError, token::Token};
+use std::rc::Rc;
 
+use crate::{expr::Expr, loxerror::LoxError};
+
+/// Statements that evaluate expressions themselves carry the line they start
+/// on, last in the tuple variants, for runtime errors to report
 #[derive(Debug)]
 pub(crate) enum Statement {
-    Print(Box<Expr>),
-    If(Box<Expr>, Box<Self>, Option<Box<Self>>),
-    Expression(Box<Expr>),
+    Print(Box<Expr>, usize),
+    If(Box<Expr>, Box<Self>, Option<Box<Self>>, usize),
+    /// A loop, optionally labelled so nested loops can `break`/`continue` it.
+    /// The increment of a desugared `for` loop runs after every iteration,
+    /// including those cut short by `continue`
+    While {
+        condition: Box<Expr>,
+        body: Box<Self>,
+        increment: Option<Box<Expr>>,
+        label: Option<String>,
+        line: usize,
+    },
+    Expression(Box<Expr>, usize),
     Block(Vec<Statement>),
 
-    Function {
-        name: Token,
-        args: Vec<Token>,
+    Function(Rc<FunctionDeclaration>),
+    Class(Rc<ClassDeclaration>),
+    Trait(Rc<TraitDeclaration>),
+    Var {
+        name: String,
+        initializer: Option<Expr>,
+        line: usize,
+    },
+    Return(Option<Box<Expr>>, usize),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Throw(Box<Expr>, usize),
+    /// Runs the file at `path`, binding its top-level definitions to `name`
+    Import {
+        path: String,
+        name: String,
+        line: usize,
+    },
+    /// A `try` block with at least one of a `catch` clause or a `finally`
+    /// block, the latter running however the others complete
+    Try {
         body: Vec<Statement>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Statement>>,
     },
-    Var { name: Token, initializer: &Option<Expr> },
+}
+
+/// The handler of a `try` statement, binding the caught value to `name`
+#[derive(Debug)]
+pub(crate) struct CatchClause {
+    pub(crate) name: String,
+    pub(crate) body: Vec<Statement>,
+}
+
+/// The parsed form of a function, shared between the syntax tree and every
+/// closure created from it
+#[derive(Debug)]
+pub(crate) struct FunctionDeclaration {
+    pub(crate) name: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) body: Vec<Statement>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ClassDeclaration {
+    pub(crate) name: String,
+    pub(crate) superclass: Option<Expr>,
+    /// The traits listed after `with`, in order
+    pub(crate) traits: Vec<Expr>,
+    pub(crate) methods: Vec<Rc<FunctionDeclaration>>,
+    /// Methods without a parameter list, run when their property is read
+    pub(crate) getters: Vec<Rc<FunctionDeclaration>>,
+    /// Methods declared with `class`, called on the class object
+    pub(crate) class_methods: Vec<Rc<FunctionDeclaration>>,
+    /// Fields declared with `class var`, set on the class object
+    pub(crate) fields: Vec<(String, Option<Expr>)>,
+    pub(crate) line: usize,
+}
+
+/// A named set of methods and getters that classes include with `with`
+#[derive(Debug)]
+pub(crate) struct TraitDeclaration {
+    pub(crate) name: String,
+    pub(crate) methods: Vec<Rc<FunctionDeclaration>>,
+    pub(crate) getters: Vec<Rc<FunctionDeclaration>>,
 }
 
 impl Statement {
+    /// The line the statement starts on, for those that can raise an error
+    /// themselves rather than only through the statements they hold
+    pub(crate) fn line(&self) -> Option<usize> {
+        match self {
+            Statement::Print(_, line)
+            | Statement::If(.., line)
+            | Statement::While { line, .. }
+            | Statement::Expression(_, line)
+            | Statement::Var { line, .. }
+            | Statement::Return(_, line)
+            | Statement::Throw(_, line)
+            | Statement::Import { line, .. } => Some(*line),
+            Statement::Class(declaration) => Some(declaration.line),
+            Statement::Block(_)
+            | Statement::Function(_)
+            | Statement::Trait(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Try { .. } => None,
+        }
+    }
+
     pub(crate) fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> Result<R, LoxError> {
         match self {
-            Statement::Print(expr) => visitor.visit_print_statement(expr),
-            Statement::If(condition, if_branch, else_branch) => {
-                visitor.visit_if_statement(condition, if_branch, else_branch)
+            Statement::Print(expr, _) => visitor.visit_print_statement(expr),
+            Statement::If(condition, if_branch, else_branch, _) => {
+                visitor.visit_if_statement(condition, if_branch, else_branch.as_deref())
             }
-            Statement::Expression(expr) => visitor.visit_expression_statement(expr),
+            Statement::While {
+                condition,
+                body,
+                increment,
+                label,
+                ..
+            } => visitor.visit_while_statement(
+                condition,
+                body,
+                increment.as_deref(),
+                label.as_deref(),
+            ),
+            Statement::Expression(expr, _) => visitor.visit_expression_statement(expr),
             Statement::Block(statement) => visitor.visit_block_statement(statement),
-            Statement::Function {name, args, body} => visitor.visit_function_statement(name, args, body),
-            Statement::Var { name, initializer } => visitor.visit_var_statement(name, initializer),
+            Statement::Function(declaration) => visitor.visit_function_statement(declaration),
+            Statement::Class(declaration) => visitor.visit_class_statement(declaration),
+            Statement::Trait(declaration) => visitor.visit_trait_statement(declaration),
+            Statement::Return(value, _) => visitor.visit_return_statement(value.as_deref()),
+            Statement::Break(label) => visitor.visit_break_statement(label.as_deref()),
+            Statement::Continue(label) => visitor.visit_continue_statement(label.as_deref()),
+            Statement::Throw(value, _) => visitor.visit_throw_statement(value),
+            Statement::Import { path, name, .. } => visitor.visit_import_statement(path, name),
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => visitor.visit_try_statement(body, catch.as_ref(), finally.as_deref()),
+            Statement::Var {
+                name, initializer, ..
+            } => visitor.visit_var_statement(name, initializer.as_ref()),
         }
     }
 }
 
 pub(crate) trait Visitor<R> {
-    fn visit_print_statement(&mut self, expr: &Box<Expr>) -> Result<R, LoxError>;
+    fn visit_print_statement(&mut self, expr: &Expr) -> Result<R, LoxError>;
     fn visit_if_statement(
         &mut self,
-        condition: &Box<Expr>,
-        if_branch: &Box<Statement>,
-        else_branch: &Option<Box<Statement>>,
+        condition: &Expr,
+        if_branch: &Statement,
+        else_branch: Option<&Statement>,
     ) -> Result<R, LoxError>;
-    fn visit_expression_statement(&mut self, expr: &Box<Expr>) -> Result<R, LoxError>;
-    fn visit_block_statement(&mut self, statements: &Vec<Statement>) -> Result<R, LoxError>;
+    fn visit_while_statement(
+        &mut self,
+        condition: &Expr,
+        body: &Statement,
+        increment: Option<&Expr>,
+        label: Option<&str>,
+    ) -> Result<R, LoxError>;
+    fn visit_expression_statement(&mut self, expr: &Expr) -> Result<R, LoxError>;
+    fn visit_block_statement(&mut self, statements: &[Statement]) -> Result<R, LoxError>;
     fn visit_function_statement(
         &mut self,
-        name: &Token,
-        args: &Vec<Token>,
-        body: &Vec<Statement>,
+        declaration: &Rc<FunctionDeclaration>,
+    ) -> Result<R, LoxError>;
+    fn visit_class_statement(&mut self, declaration: &Rc<ClassDeclaration>) -> Result<R, LoxError>;
+    fn visit_trait_statement(&mut self, declaration: &Rc<TraitDeclaration>) -> Result<R, LoxError>;
+    fn visit_var_statement(
+        &mut self,
+        name: &str,
+        initializer: Option<&Expr>,
+    ) -> Result<R, LoxError>;
+    fn visit_return_statement(&mut self, value: Option<&Expr>) -> Result<R, LoxError>;
+    fn visit_break_statement(&mut self, label: Option<&str>) -> Result<R, LoxError>;
+    fn visit_continue_statement(&mut self, label: Option<&str>) -> Result<R, LoxError>;
+    fn visit_throw_statement(&mut self, value: &Expr) -> Result<R, LoxError>;
+    fn visit_import_statement(&mut self, path: &str, name: &str) -> Result<R, LoxError>;
+    fn visit_try_statement(
+        &mut self,
+        body: &[Statement],
+        catch: Option<&CatchClause>,
+        finally: Option<&[Statement]>,
     ) -> Result<R, LoxError>;
-    fn visit_var_statement(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<R, LoxError>;
 }
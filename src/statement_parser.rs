@@ -1,22 +1,27 @@
+use std::rc::Rc;
+
 use tracing::trace;
 
 use crate::{
-    expr::Expr,
+    expr::{Depth, Expr, Literal},
     expr_parser::ExprParser,
     loxerror::LoxError,
-    statement::Statement,
+    statement::{CatchClause, ClassDeclaration, FunctionDeclaration, Statement, TraitDeclaration},
     string_indexer::StringIndexer,
     token::{Token, TokenStore},
 };
 
 pub(crate) struct StatementParser {
     expr_parser: ExprParser,
+    /// Labels of the loops enclosing the statement being parsed, innermost last
+    loops: Vec<Option<String>>,
 }
 
 impl StatementParser {
     pub(crate) fn new() -> Self {
         Self {
             expr_parser: ExprParser::new(),
+            loops: Vec::new(),
         }
     }
 
@@ -51,9 +56,19 @@ impl StatementParser {
     ) -> Result<Statement, LoxError> {
         let declaration = match token_store.peek() {
             Some(token) => match token {
-                Token::Var => todo!(),
-                Token::Fun => self.function(token_store, string_indexer, "function"),
-                Token::Class => todo!(),
+                Token::Var => self.var_declaration(token_store, string_indexer),
+                // An anonymous function used as an expression statement
+                Token::Fun if token_store.peek_next() == Some(&Token::LeftParen) => {
+                    self.parse_statement(token_store, string_indexer)
+                }
+                Token::Fun => {
+                    token_store.consume();
+                    let declaration = self.function(token_store, string_indexer, "function")?;
+                    Ok(Statement::Function(declaration))
+                }
+                Token::Class => self.class_declaration(token_store, string_indexer),
+                Token::Trait => self.trait_declaration(token_store, string_indexer),
+                Token::Import => self.import_declaration(token_store, string_indexer),
                 _ => self.parse_statement(token_store, string_indexer),
             },
             None => Err(LoxError::UnexpectedEof),
@@ -72,26 +87,107 @@ impl StatementParser {
             Some(token) => match token {
                 Token::Print => self.print_statement(token_store, string_indexer),
                 Token::If => self.if_statement(token_store, string_indexer),
+                // A brace opens a block, unless it starts a map literal such as
+                // `{"a": 1}` or `{x: 1}`
+                Token::LeftBrace if Self::is_map_literal(token_store) => {
+                    self.parse_expression_statement(token_store, string_indexer)
+                }
                 Token::LeftBrace => {
                     token_store.consume();
-                    return Ok(Statement::Block(self.block(token_store, string_indexer)?));
+                    Ok(Statement::Block(self.block(token_store, string_indexer)?))
+                }
+                Token::While => self.while_statement(token_store, string_indexer, None),
+                Token::Return => self.return_statement(token_store, string_indexer),
+                Token::For => self.for_statement(token_store, string_indexer, None),
+                Token::Break => self.break_statement(token_store, string_indexer),
+                Token::Continue => self.continue_statement(token_store, string_indexer),
+                Token::Throw => self.throw_statement(token_store, string_indexer),
+                Token::Try => self.try_statement(token_store, string_indexer),
+                Token::Identifier(_) if token_store.peek_next() == Some(&Token::Colon) => {
+                    self.labelled_statement(token_store, string_indexer)
                 }
-                Token::While => todo!(),
-                Token::Return => todo!(),
-                Token::For => todo!(),
                 _ => self.parse_expression_statement(token_store, string_indexer),
             },
             None => Err(LoxError::UnexpectedEof),
         }
     }
 
+    /// Looks for a key and a colon after the brace, skipping prefix operators
+    /// so `{-1: 2}` is a map. An identifier and a colon are a label when a
+    /// loop follows. Keys any longer than that, like `{a + b: 1}`, are only
+    /// read as maps where an expression is expected
+    fn is_map_literal(token_store: &TokenStore) -> bool {
+        let mut key = 1;
+        while matches!(
+            token_store.peek_nth(key),
+            Some(Token::Minus | Token::Bang | Token::Tilde)
+        ) {
+            key += 1;
+        }
+
+        if token_store.peek_nth(key + 1) != Some(&Token::Colon) {
+            return false;
+        }
+        match token_store.peek_nth(key) {
+            Some(Token::Identifier(_)) if key == 1 => !matches!(
+                token_store.peek_nth(key + 2),
+                Some(Token::While | Token::For)
+            ),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
     fn parse_expression_statement(
         &mut self,
         token_store: &mut TokenStore,
         string_indexer: &StringIndexer,
     ) -> Result<Statement, LoxError> {
-        let expr = self.expr_parser.parse(token_store, string_indexer);
-        Ok(Statement::Expression(Box::new(expr)))
+        let line = token_store.line();
+        let expr = self.expr_parser.parse(token_store, string_indexer)?;
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok(Statement::Expression(Box::new(expr), line))
+    }
+
+    fn var_declaration(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::Var)?;
+        token_store.consume();
+
+        let (name, initializer) = self.variable(token_store, string_indexer)?;
+        Ok(Statement::Var {
+            name,
+            initializer,
+            line,
+        })
+    }
+
+    /// Parses the name, optional initializer and semicolon following `var`
+    fn variable(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<(String, Option<Expr>), LoxError> {
+        let name = self.identifier(token_store, string_indexer, "Expected a variable name")?;
+
+        let initializer = if token_store.is(Token::Equal) {
+            token_store.consume();
+            Some(self.expr_parser.parse(token_store, string_indexer)?)
+        } else {
+            None
+        };
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok((name, initializer))
     }
 
     fn print_statement(
@@ -99,15 +195,16 @@ impl StatementParser {
         token_store: &mut TokenStore,
         string_indexer: &StringIndexer,
     ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
         token_store.expect(Token::Print)?;
         token_store.consume();
 
-        let value: Expr = self.expr_parser.parse(token_store, string_indexer);
+        let value: Expr = self.expr_parser.parse(token_store, string_indexer)?;
 
         token_store.expect(Token::Semicolon)?;
         token_store.consume();
 
-        Ok(Statement::Print(Box::new(value)))
+        Ok(Statement::Print(Box::new(value), line))
     }
 
     fn if_statement(
@@ -115,12 +212,13 @@ impl StatementParser {
         token_store: &mut TokenStore,
         string_indexer: &StringIndexer,
     ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
         token_store.expect(Token::If)?;
         token_store.consume();
         token_store.expect(Token::LeftParen)?;
         token_store.consume();
 
-        let condition = self.expr_parser.parse(token_store, string_indexer);
+        let condition = self.expr_parser.parse(token_store, string_indexer)?;
 
         token_store.expect(Token::RightParen)?;
         token_store.consume();
@@ -142,9 +240,309 @@ impl StatementParser {
             Box::new(condition),
             Box::new(if_branch),
             else_branch.map(Box::new),
+            line,
         ))
     }
 
+    fn return_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::Return)?;
+        token_store.consume();
+
+        let value = if token_store.is(Token::Semicolon) {
+            None
+        } else {
+            Some(self.expr_parser.parse(token_store, string_indexer)?)
+        };
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok(Statement::Return(value.map(Box::new), line))
+    }
+
+    fn labelled_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let label = self.identifier(token_store, string_indexer, "Expected a loop label")?;
+        token_store.expect(Token::Colon)?;
+        token_store.consume();
+
+        match token_store.peek() {
+            Some(Token::While) => self.while_statement(token_store, string_indexer, Some(label)),
+            Some(Token::For) => self.for_statement(token_store, string_indexer, Some(label)),
+            Some(_) => Err(LoxError::InvalidStatement {
+                error: format!("Label '{}' must be followed by a loop", label),
+            }),
+            None => Err(LoxError::UnexpectedEof),
+        }
+    }
+
+    /// Parses a loop body, keeping track of the loop so `break` and
+    /// `continue` can be validated
+    fn loop_body(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+        label: Option<String>,
+    ) -> Result<Statement, LoxError> {
+        self.loops.push(label);
+        let body = self.parse_statement(token_store, string_indexer);
+        self.loops.pop();
+        body
+    }
+
+    fn while_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+        label: Option<String>,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::While)?;
+        token_store.consume();
+        token_store.expect(Token::LeftParen)?;
+        token_store.consume();
+
+        let condition = self.expr_parser.parse(token_store, string_indexer)?;
+
+        token_store.expect(Token::RightParen)?;
+        token_store.consume();
+
+        let body = self.loop_body(token_store, string_indexer, label.clone())?;
+
+        Ok(Statement::While {
+            condition: Box::new(condition),
+            body: Box::new(body),
+            increment: None,
+            label,
+            line,
+        })
+    }
+
+    /// A for loop is desugared into a while loop wrapped in a block holding
+    /// the initializer, with the increment run after each iteration
+    fn for_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+        label: Option<String>,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::For)?;
+        token_store.consume();
+        token_store.expect(Token::LeftParen)?;
+        token_store.consume();
+
+        let initializer = match token_store.peek() {
+            Some(Token::Semicolon) => {
+                token_store.consume();
+                None
+            }
+            Some(Token::Var) => Some(self.var_declaration(token_store, string_indexer)?),
+            Some(_) => Some(self.parse_expression_statement(token_store, string_indexer)?),
+            None => return Err(LoxError::UnexpectedEof),
+        };
+
+        let condition = if token_store.is(Token::Semicolon) {
+            Expr::Literal(Literal::Bool(true))
+        } else {
+            self.expr_parser.parse(token_store, string_indexer)?
+        };
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        let increment = if token_store.is(Token::RightParen) {
+            None
+        } else {
+            Some(self.expr_parser.parse(token_store, string_indexer)?)
+        };
+        token_store.expect(Token::RightParen)?;
+        token_store.consume();
+
+        let body = self.loop_body(token_store, string_indexer, label.clone())?;
+
+        let mut body = Statement::While {
+            condition: Box::new(condition),
+            body: Box::new(body),
+            increment: increment.map(Box::new),
+            label,
+            line,
+        };
+        if let Some(initializer) = initializer {
+            body = Statement::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn break_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        token_store.expect(Token::Break)?;
+        token_store.consume();
+
+        let label = self.loop_jump_label(token_store, string_indexer, "break")?;
+        Ok(Statement::Break(label))
+    }
+
+    fn continue_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        token_store.expect(Token::Continue)?;
+        token_store.consume();
+
+        let label = self.loop_jump_label(token_store, string_indexer, "continue")?;
+        Ok(Statement::Continue(label))
+    }
+
+    fn import_declaration(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::Import)?;
+        token_store.consume();
+
+        let path = match token_store.next() {
+            // The indexed span includes the surrounding quotes
+            Some(Token::String(string_id)) => {
+                let path = string_indexer.get_string(*string_id)?;
+                path.trim_matches('"').to_string()
+            }
+            Some(_) => {
+                return Err(LoxError::InvalidToken {
+                    error: "Expected a module path after 'import'",
+                })
+            }
+            None => return Err(LoxError::UnexpectedEof),
+        };
+
+        token_store.expect(Token::As)?;
+        token_store.consume();
+        let name = self.identifier(token_store, string_indexer, "Expected a module name")?;
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok(Statement::Import { path, name, line })
+    }
+
+    fn throw_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::Throw)?;
+        token_store.consume();
+
+        let value = self.expr_parser.parse(token_store, string_indexer)?;
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok(Statement::Throw(Box::new(value), line))
+    }
+
+    fn try_statement(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        token_store.expect(Token::Try)?;
+        token_store.consume();
+        token_store.expect(Token::LeftBrace)?;
+        token_store.consume();
+        let body = self.block(token_store, string_indexer)?;
+
+        let catch = if token_store.is(Token::Catch) {
+            token_store.consume();
+            token_store.expect(Token::LeftParen)?;
+            token_store.consume();
+            let name = self.identifier(token_store, string_indexer, "Expected a catch variable")?;
+            token_store.expect(Token::RightParen)?;
+            token_store.consume();
+
+            token_store.expect(Token::LeftBrace)?;
+            token_store.consume();
+            let body = self.block(token_store, string_indexer)?;
+            Some(CatchClause { name, body })
+        } else {
+            None
+        };
+
+        let finally = if token_store.is(Token::Finally) {
+            token_store.consume();
+            token_store.expect(Token::LeftBrace)?;
+            token_store.consume();
+            Some(self.block(token_store, string_indexer)?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(LoxError::InvalidStatement {
+                error: "Expected 'catch' or 'finally' after a try block".to_string(),
+            });
+        }
+
+        Ok(Statement::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    /// Parses the optional label and closing `;` of a `break` or `continue`,
+    /// checking it is used inside a matching loop
+    fn loop_jump_label(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+        keyword: &'static str,
+    ) -> Result<Option<String>, LoxError> {
+        if self.loops.is_empty() {
+            return Err(LoxError::InvalidStatement {
+                error: format!("Can't use '{}' outside of a loop", keyword),
+            });
+        }
+
+        let label = if token_store.is(Token::Semicolon) {
+            None
+        } else {
+            Some(self.identifier(token_store, string_indexer, "Expected a loop label")?)
+        };
+
+        if let Some(name) = &label {
+            if !self
+                .loops
+                .iter()
+                .any(|loop_label| loop_label.as_ref() == Some(name))
+            {
+                return Err(LoxError::InvalidStatement {
+                    error: format!("No enclosing loop labelled '{}'", name),
+                });
+            }
+        }
+
+        token_store.expect(Token::Semicolon)?;
+        token_store.consume();
+
+        Ok(label)
+    }
+
     fn block(
         &mut self,
         token_store: &mut TokenStore,
@@ -153,11 +551,11 @@ impl StatementParser {
         trace!("block entered");
         let mut statements = Vec::new();
 
-        while !token_store.is(Token::RightBrace) {
+        while !token_store.is(Token::RightBrace) && !token_store.is(Token::Eof) {
             statements.push(self.parse_declaration(token_store, string_indexer)?);
         }
 
-        let _ = token_store.expect(Token::RightBrace);
+        token_store.expect(Token::RightBrace)?;
         token_store.consume();
 
         trace!("block exit, statements: {:?}", statements);
@@ -168,38 +566,237 @@ impl StatementParser {
         &mut self,
         token_store: &mut TokenStore,
         string_indexer: &StringIndexer,
-        _arg: &'static str,
-    ) -> Result<Statement, LoxError> {
-        let name = token_store.next().expect("Expected a name token").clone();
+        kind: &'static str,
+    ) -> Result<Rc<FunctionDeclaration>, LoxError> {
+        trace!("parsing {kind}");
+        let name = self.identifier(token_store, string_indexer, "Expected a function name")?;
+        let args = self.parameters(token_store, string_indexer)?;
+
+        token_store.expect(Token::LeftBrace)?;
+        token_store.consume();
+        let body = self.function_body(token_store, string_indexer)?;
+
+        Ok(Rc::new(FunctionDeclaration { name, args, body }))
+    }
+
+    /// Parses an anonymous function following the `fun` keyword, with either a
+    /// block body or an `=>` arrow followed by the single expression it returns
+    pub(crate) fn lambda(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Rc<FunctionDeclaration>, LoxError> {
+        trace!("parsing lambda");
+        let args = self.parameters(token_store, string_indexer)?;
 
-        let _ = token_store.expect(Token::LeftParen);
+        let body = match token_store.next() {
+            Some(Token::LeftBrace) => self.function_body(token_store, string_indexer)?,
+            Some(Token::Arrow) => {
+                let line = token_store.line();
+                let value = self.expr_parser.parse(token_store, string_indexer)?;
+                vec![Statement::Return(Some(Box::new(value)), line)]
+            }
+            Some(_) => {
+                return Err(LoxError::InvalidToken {
+                    error: "Expected '{' or '=>' after lambda parameters",
+                })
+            }
+            None => return Err(LoxError::UnexpectedEof),
+        };
+
+        Ok(Rc::new(FunctionDeclaration {
+            name: "lambda".to_string(),
+            args,
+            body,
+        }))
+    }
+
+    fn parameters(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Vec<String>, LoxError> {
+        token_store.expect(Token::LeftParen)?;
         token_store.consume();
 
-        let mut args: Vec<Token> = vec![];
+        let mut args: Vec<String> = vec![];
         while !token_store.is(Token::RightParen) {
-            if args.len() > 255 {
+            if args.len() >= 255 {
                 return Err(LoxError::InvalidToken {
                     error: "Too many arguments to function call",
                 });
             }
+            args.push(self.identifier(token_store, string_indexer, "Expected a parameter name")?);
+
+            if !token_store.is(Token::Comma) {
+                break;
+            }
+            token_store.consume();
+        }
+        token_store.expect(Token::RightParen)?;
+        token_store.consume();
+
+        Ok(args)
+    }
+
+    /// Parses the statements of a function body, after its opening brace
+    fn function_body(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Vec<Statement>, LoxError> {
+        // Loops outside the function can't be broken out of from its body
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let body = self.block(token_store, string_indexer);
+        self.loops = enclosing_loops;
+        body
+    }
+
+    fn class_declaration(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        let line = token_store.line();
+        token_store.expect(Token::Class)?;
+        token_store.consume();
+
+        let name = self.identifier(token_store, string_indexer, "Expected a class name")?;
+
+        let superclass = if token_store.is(Token::Less) {
+            token_store.consume();
+            let superclass =
+                self.identifier(token_store, string_indexer, "Expected a superclass name")?;
+            Some(Expr::Variable(superclass, Depth::default()))
+        } else {
+            None
+        };
+
+        let mut traits = vec![];
+        if token_store.is(Token::With) {
+            token_store.consume();
+            loop {
+                let name = self.identifier(token_store, string_indexer, "Expected a trait name")?;
+                traits.push(Expr::Variable(name, Depth::default()));
+                if !token_store.is(Token::Comma) {
+                    break;
+                }
+                token_store.consume();
+            }
+        }
+
+        token_store.expect(Token::LeftBrace)?;
+        token_store.consume();
 
-            match token_store.next() {
-                Some(token) => match token {
-                    Token::Identifier(_) => args.push(token.clone()),
-                    t => trace!("Expected an identifier, found {:?}", t),
-                },
-                None => return Err(LoxError::UnexpectedEof),
+        let mut methods = vec![];
+        let mut getters = vec![];
+        let mut class_methods = vec![];
+        let mut fields = vec![];
+        while !token_store.is(Token::RightBrace) && !token_store.is(Token::Eof) {
+            if token_store.is(Token::Class) {
+                token_store.consume();
+                if token_store.is(Token::Var) {
+                    token_store.consume();
+                    fields.push(self.variable(token_store, string_indexer)?);
+                } else {
+                    class_methods.push(self.function(
+                        token_store,
+                        string_indexer,
+                        "class method",
+                    )?);
+                }
+            } else if token_store.peek_next() == Some(&Token::LeftBrace) {
+                getters.push(self.getter(token_store, string_indexer)?);
+            } else {
+                methods.push(self.function(token_store, string_indexer, "method")?);
             }
         }
-        let _ = token_store.expect(Token::RightParen);
+
+        token_store.expect(Token::RightBrace)?;
         token_store.consume();
 
-        let _ = token_store.expect(Token::LeftBrace);
+        Ok(Statement::Class(Rc::new(ClassDeclaration {
+            name,
+            superclass,
+            traits,
+            methods,
+            getters,
+            class_methods,
+            fields,
+            line,
+        })))
+    }
+
+    fn trait_declaration(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Statement, LoxError> {
+        token_store.expect(Token::Trait)?;
         token_store.consume();
 
-        let body = self.block(token_store, string_indexer)?;
+        let name = self.identifier(token_store, string_indexer, "Expected a trait name")?;
+
+        token_store.expect(Token::LeftBrace)?;
+        token_store.consume();
+
+        let mut methods = vec![];
+        let mut getters = vec![];
+        while !token_store.is(Token::RightBrace) && !token_store.is(Token::Eof) {
+            if token_store.is(Token::Class) {
+                return Err(LoxError::InvalidToken {
+                    error: "Traits can't declare class members",
+                });
+            } else if token_store.peek_next() == Some(&Token::LeftBrace) {
+                getters.push(self.getter(token_store, string_indexer)?);
+            } else {
+                methods.push(self.function(token_store, string_indexer, "method")?);
+            }
+        }
+
+        token_store.expect(Token::RightBrace)?;
+        token_store.consume();
+
+        Ok(Statement::Trait(Rc::new(TraitDeclaration {
+            name,
+            methods,
+            getters,
+        })))
+    }
+
+    /// Parses a getter, a method declared without a parameter list
+    fn getter(
+        &mut self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+    ) -> Result<Rc<FunctionDeclaration>, LoxError> {
+        trace!("parsing getter");
+        let name = self.identifier(token_store, string_indexer, "Expected a getter name")?;
 
-        Ok(Statement::Function { name, args, body })
+        token_store.expect(Token::LeftBrace)?;
+        token_store.consume();
+        let body = self.function_body(token_store, string_indexer)?;
+
+        Ok(Rc::new(FunctionDeclaration {
+            name,
+            args: vec![],
+            body,
+        }))
+    }
+
+    fn identifier(
+        &self,
+        token_store: &mut TokenStore,
+        string_indexer: &StringIndexer,
+        error: &'static str,
+    ) -> Result<String, LoxError> {
+        match token_store.next() {
+            Some(Token::Identifier(string_id)) => {
+                Ok(string_indexer.get_string(*string_id)?.to_string())
+            }
+            Some(_) => Err(LoxError::InvalidToken { error }),
+            None => Err(LoxError::UnexpectedEof),
+        }
     }
 }
 
@@ -209,18 +806,286 @@ mod test {
 
     use super::*;
 
-    fn setup(source: &str) -> Vec<Statement> {
+    fn setup(source: &str) -> Result<Vec<Statement>, LoxError> {
         let mut lexer = Lexer::new();
         let mut string_indexer = StringIndexer::new(source);
-        let _ = lexer.tokenize(&mut string_indexer, source);
-        //let mut parser = StatementParser::new(lexer.get());
-        //parser.run()
-        todo!()
+        let mut token_store = lexer.tokenize(&mut string_indexer, source).unwrap();
+        let mut parser = StatementParser::new();
+        parser.run(&mut token_store, &string_indexer)
     }
 
     #[test]
     fn test() {
-        let response = setup("print \"hello\"");
-        assert_eq!(format!("{:?}", response), "");
+        let response = setup("print \"hello\";").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Print(Literal(String(\"hello\")), 1)]"
+        );
+    }
+
+    #[test]
+    fn test_var_declaration() {
+        let response = setup("var a = 1; var b;").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Var { name: \"a\", initializer: Some(Literal(Integer(1))), line: 1 }, \
+             Var { name: \"b\", initializer: None, line: 1 }]"
+        );
+
+        assert!(setup("var = 1;").is_err());
+        assert!(setup("var a = 1").is_err());
+    }
+
+    #[test]
+    fn test_block() {
+        let response = setup("{ var a = 1; a = 2; }").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Block([Var { name: \"a\", initializer: Some(Literal(Integer(1))), line: 1 }, \
+             Expression(Assign(\"a\", Literal(Integer(2)), Depth(None)), 1)])]"
+        );
+
+        assert!(setup("{ var a = 1;").is_err());
+    }
+
+    #[test]
+    fn test_while() {
+        let response = setup("while (true) print 1;").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[While { condition: Literal(Bool(true)), body: Print(Literal(Integer(1)), 1), \
+             increment: None, label: None, line: 1 }]"
+        );
+
+        assert!(setup("while true print 1;").is_err());
+    }
+
+    #[test]
+    fn test_for() {
+        let response = setup("for (var i = 0; i < 2; i = i + 1) print i;").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Block([Var { name: \"i\", initializer: Some(Literal(Integer(0))), line: 1 }, \
+             While { condition: Binary(Variable(\"i\", Depth(None)), LessThan, Literal(Integer(2))), \
+             body: Print(Variable(\"i\", Depth(None)), 1), \
+             increment: Some(Assign(\"i\", Binary(Variable(\"i\", Depth(None)), Plus, Literal(Integer(1))), Depth(None))), \
+             label: None, line: 1 }])]"
+        );
+
+        let response = setup("for (;;) print 1;").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[While { condition: Literal(Bool(true)), body: Print(Literal(Integer(1)), 1), \
+             increment: None, label: None, line: 1 }]"
+        );
+
+        let response = setup(include_str!("../resources/loop.lox")).unwrap();
+        assert_eq!(response.len(), 3);
+        assert!(matches!(response[1], Statement::While { .. }));
+        match &response[2] {
+            Statement::Block(statements) => {
+                assert!(matches!(
+                    statements[1],
+                    Statement::While {
+                        increment: Some(_),
+                        ..
+                    }
+                ))
+            }
+            statement => panic!("Expected a desugared for loop, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let response = setup("import \"lib/math.lox\" as math;").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Import { path: \"lib/math.lox\", name: \"math\", line: 1 }]"
+        );
+
+        assert!(setup("import math;").is_err());
+        assert!(setup("import \"math.lox\";").is_err());
+        assert!(setup("import \"math.lox\" as math").is_err());
+    }
+
+    #[test]
+    fn test_try() {
+        let response =
+            setup("try { throw 1; } catch (e) { print e; } finally { print 2; }").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Try { body: [Throw(Literal(Integer(1)), 1)], \
+             catch: Some(CatchClause { name: \"e\", body: [Print(Variable(\"e\", Depth(None)), 1)] }), \
+             finally: Some([Print(Literal(Integer(2)), 1)]) }]"
+        );
+
+        assert!(setup("try {} finally {}").is_ok());
+        assert!(setup("try {}").is_err());
+        assert!(setup("try {} catch {}").is_err());
+        assert!(setup("throw 1").is_err());
+    }
+
+    #[test]
+    fn test_map_statement() {
+        let response = setup("{\"a\": 1};").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Expression(Map([(Literal(String(\"a\")), Literal(Integer(1)))]), 1)]"
+        );
+
+        let response = setup("{}").unwrap();
+        assert_eq!(format!("{:?}", response), "[Block([])]");
+
+        let response = setup("{ outer: while (true) break outer; }").unwrap();
+        assert!(matches!(response[0], Statement::Block(_)));
+
+        for source in ["{x: 1};", "{-1: 2};", "{!true: 3};"] {
+            let response = setup(source).unwrap();
+            assert!(
+                format!("{:?}", response).starts_with("[Expression(Map("),
+                "{}",
+                source
+            );
+        }
+
+        // Longer keys need the map to be in expression position
+        assert!(setup("{1 + 1: 2};").is_err());
+        assert!(setup("({1 + 1: 2});").is_ok());
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let response = setup("outer: while (true) { break outer; continue; }").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[While { condition: Literal(Bool(true)), \
+             body: Block([Break(Some(\"outer\")), Continue(None)]), \
+             increment: None, label: Some(\"outer\"), line: 1 }]"
+        );
+
+        assert!(setup("break;").is_err());
+        assert!(setup("continue;").is_err());
+        assert!(setup("while (true) break missing;").is_err());
+        assert!(setup("while (true) { fun f() { break; } }").is_err());
+        assert!(setup("while (true) { var f = fun () { break; }; }").is_err());
+        assert!(setup("outer: print 1;").is_err());
+        assert!(setup("while (true) break").is_err());
+    }
+
+    #[test]
+    fn test_return() {
+        let response = setup("fun f() { if (true) return 1; return; }").unwrap();
+        assert_eq!(
+            format!("{:?}", response),
+            "[Function(FunctionDeclaration { name: \"f\", args: [], body: [\
+             If(Literal(Bool(true)), Return(Some(Literal(Integer(1))), 1), None, 1), \
+             Return(None, 1)] })]"
+        );
+
+        assert!(setup("return 1").is_err());
+    }
+
+    #[test]
+    fn test_function() {
+        let response = setup(include_str!("../resources/function.lox")).unwrap();
+        assert_eq!(response.len(), 3);
+        match &response[1] {
+            Statement::Function(declaration) => {
+                assert_eq!(declaration.name, "doPrint");
+                assert_eq!(declaration.args, vec!["first", "second"]);
+                assert_eq!(declaration.body.len(), 1);
+            }
+            statement => panic!("Expected a function, found {:?}", statement),
+        }
+
+        assert!(setup("fun f(a b) {}").is_err());
+        assert!(setup("fun (a) {}").is_err());
+    }
+
+    #[test]
+    fn test_class() {
+        let response =
+            setup("class Foo { init(a) { this.a = a; } get() { return this.a; } }").unwrap();
+        match &response[0] {
+            Statement::Class(declaration) => {
+                assert_eq!(declaration.name, "Foo");
+                let methods: Vec<&str> = declaration
+                    .methods
+                    .iter()
+                    .map(|method| method.name.as_str())
+                    .collect();
+                assert_eq!(methods, vec!["init", "get"]);
+            }
+            statement => panic!("Expected a class, found {:?}", statement),
+        }
+
+        let response = setup(
+            "class Foo { class var count = 0; class var empty; class create() { return Foo(); } \
+             area { return 1; } size() { return 2; } }",
+        )
+        .unwrap();
+        match &response[0] {
+            Statement::Class(declaration) => {
+                let names = |methods: &Vec<Rc<FunctionDeclaration>>| -> Vec<String> {
+                    methods.iter().map(|method| method.name.clone()).collect()
+                };
+                assert_eq!(names(&declaration.methods), vec!["size"]);
+                assert_eq!(names(&declaration.getters), vec!["area"]);
+                assert_eq!(names(&declaration.class_methods), vec!["create"]);
+                assert_eq!(
+                    format!("{:?}", declaration.fields),
+                    "[(\"count\", Some(Literal(Integer(0)))), (\"empty\", None)]"
+                );
+            }
+            statement => panic!("Expected a class, found {:?}", statement),
+        }
+        assert!(setup("class Foo { class var; }").is_err());
+        assert!(setup("class Foo { class {} }").is_err());
+
+        let response = setup("class Foo < Bar {}").unwrap();
+        match &response[0] {
+            Statement::Class(declaration) => assert_eq!(
+                format!("{:?}", declaration.superclass),
+                "Some(Variable(\"Bar\", Depth(None)))"
+            ),
+            statement => panic!("Expected a class, found {:?}", statement),
+        }
+
+        assert!(setup("class { }").is_err());
+        assert!(setup("class Foo < {}").is_err());
+        assert!(setup("class Foo { var a = 1; }").is_err());
+        assert!(setup("class Foo { init() {}").is_err());
+    }
+
+    #[test]
+    fn test_trait() {
+        let response = setup("trait Named { name() { return 1; } label { return 2; } }").unwrap();
+        match &response[0] {
+            Statement::Trait(declaration) => {
+                assert_eq!(declaration.name, "Named");
+                assert_eq!(declaration.methods[0].name, "name");
+                assert_eq!(declaration.getters[0].name, "label");
+            }
+            statement => panic!("Expected a trait, found {:?}", statement),
+        }
+
+        let response = setup("class Foo < Bar with Baz, Qux {} class Solo with Baz {}").unwrap();
+        match (&response[0], &response[1]) {
+            (Statement::Class(foo), Statement::Class(solo)) => {
+                assert_eq!(
+                    format!("{:?}", foo.traits),
+                    "[Variable(\"Baz\", Depth(None)), Variable(\"Qux\", Depth(None))]"
+                );
+                assert!(solo.superclass.is_none());
+                assert_eq!(solo.traits.len(), 1);
+            }
+            statements => panic!("Expected classes, found {:?}", statements),
+        }
+
+        assert!(setup("trait { }").is_err());
+        assert!(setup("trait Foo { class make() {} }").is_err());
+        assert!(setup("class Foo with {}").is_err());
+        assert!(setup("class Foo with Bar, {}").is_err());
     }
 }
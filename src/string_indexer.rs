@@ -38,4 +38,13 @@ impl<'src> StringIndexer<'src> {
 
         Ok(&self.content[string_ref.start..=string_ref.end])
     }
+
+    /// Counts the characters from the start of one string's span to the end
+    /// of another's, such as across the segments of an interpolated string
+    pub(crate) fn chars_between(&self, from: StringId, to: StringId) -> Result<usize, LoxError> {
+        match (self.refs.get(from), self.refs.get(to)) {
+            (Some(from), Some(to)) => Ok(self.content[from.start..=to.end].chars().count()),
+            _ => Err(LoxError::StringIndexOutOfBouds),
+        }
+    }
 }
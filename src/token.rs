@@ -1,18 +1,46 @@
-use crate::{loxerror::LoxError, string_indexer::StringId};
+use crate::{bigint::BigInt, loxerror::LoxError, string_indexer::StringId};
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Token {
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
     LeftBrace,
     RightBrace,
     Comma,
+    Colon,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
+    Arrow,
+    /// Opens an expression embedded in a string literal with `${`
+    InterpolationStart,
+    /// Closes an embedded expression, resuming the string literal
+    InterpolationEnd,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    StarStar,
+    Percent,
+    /// Integer division, spelt `~/` since `//` starts a comment
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    LessLess,
+    GreaterGreater,
+
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
 
     Bang,
     BangEqual,
@@ -25,36 +53,64 @@ pub(crate) enum Token {
 
     Identifier(StringId),
     String(StringId),
+    Integer(i64),
+    /// An integer literal too long for an `i64`
+    BigInteger(BigInt),
     Number(f64),
 
     And,
+    As,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
+    Trait,
     True,
+    Try,
     Var,
     While,
+    With,
 
     Eof,
 }
 
 pub(crate) struct TokenStore {
     inner: Vec<Token>,
+    /// The line each token starts on, counting from 1
+    lines: Vec<usize>,
     index: usize,
 }
 
 impl TokenStore {
-    pub fn new(inner: Vec<Token>) -> Self {
-        Self { inner, index: 0 }
+    pub fn new(inner: Vec<Token>, lines: Vec<usize>) -> Self {
+        Self {
+            inner,
+            lines,
+            index: 0,
+        }
+    }
+
+    /// The line the next token starts on
+    pub(crate) fn line(&self) -> usize {
+        self.lines
+            .get(self.index)
+            .or(self.lines.last())
+            .copied()
+            .unwrap_or(1)
     }
 
     pub fn consume(&mut self) {
@@ -74,6 +130,16 @@ impl TokenStore {
         self.inner.get(self.index)
     }
 
+    /// Looks at the token following the next one without consuming either
+    pub fn peek_next(&self) -> Option<&Token> {
+        self.peek_nth(1)
+    }
+
+    /// Looks `n` tokens past the next one without consuming anything
+    pub fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.inner.get(self.index + n)
+    }
+
     pub fn expect(&self, expected: Token) -> Result<(), LoxError> {
         match self.inner.get(self.index) {
             Some(token) => {
@@ -1,4 +1,7 @@
+use std::{iter::Peekable, str::CharIndices};
+
 use crate::{
+    bigint::BigInt,
     string_indexer::StringIndexer,
     token::{Token, TokenStore},
 };
@@ -16,25 +19,111 @@ impl Lexer {
         string_indexer: &mut StringIndexer,
         content: &str,
     ) -> Result<TokenStore, String> {
-        let mut line_no = 0;
+        let mut line_no = 1;
+        // The brace depth inside each string interpolation being lexed, the
+        // innermost last. Its closing brace resumes the enclosing string
+        let mut interpolations: Vec<usize> = Vec::new();
 
         let mut tokens = Vec::new();
+        // The line each token starts on, kept alongside `tokens`
+        let mut lines = Vec::new();
         let mut char_iter = content.char_indices().peekable();
 
         while char_iter.peek().is_some() {
+            let line = line_no;
             let (pos, ch) = char_iter.next().unwrap();
 
             match ch {
                 '(' => tokens.push(Token::LeftParen),
                 ')' => tokens.push(Token::RightParen),
-                '{' => tokens.push(Token::LeftBrace),
-                '}' => tokens.push(Token::RightBrace),
+                '[' => tokens.push(Token::LeftBracket),
+                ']' => tokens.push(Token::RightBracket),
+                '{' => {
+                    if let Some(depth) = interpolations.last_mut() {
+                        *depth += 1;
+                    }
+                    tokens.push(Token::LeftBrace);
+                }
+                '}' => match interpolations.last_mut() {
+                    Some(0) => {
+                        interpolations.pop();
+                        tokens.push(Token::InterpolationEnd);
+                        let interpolated = lex_string_segment(
+                            &mut char_iter,
+                            string_indexer,
+                            &mut tokens,
+                            pos,
+                            &mut line_no,
+                        )?;
+                        if interpolated {
+                            interpolations.push(0);
+                        }
+                    }
+                    Some(depth) => {
+                        *depth -= 1;
+                        tokens.push(Token::RightBrace);
+                    }
+                    None => tokens.push(Token::RightBrace),
+                },
                 ',' => tokens.push(Token::Comma),
+                ':' => tokens.push(Token::Colon),
+                '?' => match char_iter.peek() {
+                    Some((_, '?')) => {
+                        tokens.push(Token::QuestionQuestion);
+                        char_iter.next();
+                    }
+                    Some((_, '.')) => {
+                        tokens.push(Token::QuestionDot);
+                        char_iter.next();
+                    }
+                    _ => tokens.push(Token::Question),
+                },
                 '.' => tokens.push(Token::Dot),
-                '-' => tokens.push(Token::Minus),
-                '+' => tokens.push(Token::Plus),
+                '-' => match char_iter.peek() {
+                    Some((_, '=')) => {
+                        tokens.push(Token::MinusEqual);
+                        char_iter.next();
+                    }
+                    Some((_, '-')) => {
+                        tokens.push(Token::MinusMinus);
+                        char_iter.next();
+                    }
+                    _ => tokens.push(Token::Minus),
+                },
+                '+' => match char_iter.peek() {
+                    Some((_, '=')) => {
+                        tokens.push(Token::PlusEqual);
+                        char_iter.next();
+                    }
+                    Some((_, '+')) => {
+                        tokens.push(Token::PlusPlus);
+                        char_iter.next();
+                    }
+                    _ => tokens.push(Token::Plus),
+                },
                 ';' => tokens.push(Token::Semicolon),
-                '*' => tokens.push(Token::Star),
+                '*' => match char_iter.peek() {
+                    Some((_, '=')) => {
+                        tokens.push(Token::StarEqual);
+                        char_iter.next();
+                    }
+                    Some((_, '*')) => {
+                        tokens.push(Token::StarStar);
+                        char_iter.next();
+                    }
+                    _ => tokens.push(Token::Star),
+                },
+                '%' => tokens.push(Token::Percent),
+                '~' => match char_iter.peek() {
+                    Some((_, '/')) => {
+                        tokens.push(Token::TildeSlash);
+                        char_iter.next();
+                    }
+                    _ => tokens.push(Token::Tilde),
+                },
+                '&' => tokens.push(Token::Ampersand),
+                '|' => tokens.push(Token::Pipe),
+                '^' => tokens.push(Token::Caret),
                 '/' => match char_iter.peek() {
                     Some((_, next)) => {
                         if *next == '/' {
@@ -43,6 +132,9 @@ impl Lexer {
                             {
                                 char_iter.next();
                             }
+                        } else if *next == '=' {
+                            tokens.push(Token::SlashEqual);
+                            char_iter.next();
                         } else {
                             tokens.push(Token::Slash);
                         }
@@ -66,6 +158,9 @@ impl Lexer {
                         if *next == '=' {
                             tokens.push(Token::EqualEqual);
                             char_iter.next();
+                        } else if *next == '>' {
+                            tokens.push(Token::Arrow);
+                            char_iter.next();
                         } else {
                             tokens.push(Token::Equal);
                         }
@@ -77,6 +172,9 @@ impl Lexer {
                         if *next == '=' {
                             tokens.push(Token::GreaterEqual);
                             char_iter.next();
+                        } else if *next == '>' {
+                            tokens.push(Token::GreaterGreater);
+                            char_iter.next();
                         } else {
                             tokens.push(Token::Greater);
                         }
@@ -88,6 +186,9 @@ impl Lexer {
                         if *next == '=' {
                             tokens.push(Token::LessEqual);
                             char_iter.next();
+                        } else if *next == '<' {
+                            tokens.push(Token::LessLess);
+                            char_iter.next();
                         } else {
                             tokens.push(Token::Less);
                         }
@@ -96,18 +197,16 @@ impl Lexer {
                 },
 
                 '"' => {
-                    let start = pos;
-                    let mut end = start;
-                    while char_iter.peek().is_some_and(|(_, value)| *value != '"') {
-                        let (i, _) = char_iter.next().unwrap();
-                        end = i;
+                    let interpolated = lex_string_segment(
+                        &mut char_iter,
+                        string_indexer,
+                        &mut tokens,
+                        pos,
+                        &mut line_no,
+                    )?;
+                    if interpolated {
+                        interpolations.push(0);
                     }
-                    // Account for the end '"'
-                    char_iter.next();
-
-                    end += 1;
-                    let string_id = string_indexer.add_string(start, end);
-                    tokens.push(Token::String(string_id));
                 }
 
                 '0'..='9' => {
@@ -121,10 +220,20 @@ impl Lexer {
                         end = i;
                     }
 
+                    // Literals without a decimal point are integers
                     let slice = &content[start..=end];
-                    match slice.parse() {
-                        Ok(value) => tokens.push(Token::Number(value)),
-                        Err(_) => return Err(format!("unable to parse number: {}", slice)),
+                    if slice.contains('.') {
+                        match slice.parse() {
+                            Ok(value) => tokens.push(Token::Number(value)),
+                            Err(_) => return Err(format!("unable to parse number: {}", slice)),
+                        }
+                    } else if let Ok(value) = slice.parse() {
+                        tokens.push(Token::Integer(value));
+                    } else {
+                        match BigInt::parse(slice) {
+                            Some(value) => tokens.push(Token::BigInteger(value)),
+                            None => return Err(format!("unable to parse number: {}", slice)),
+                        }
                     }
                 }
 
@@ -142,21 +251,31 @@ impl Lexer {
                     let slice = &content[start..=end];
                     match slice {
                         "and" => tokens.push(Token::And),
+                        "as" => tokens.push(Token::As),
+                        "break" => tokens.push(Token::Break),
+                        "catch" => tokens.push(Token::Catch),
                         "class" => tokens.push(Token::Class),
+                        "continue" => tokens.push(Token::Continue),
                         "else" => tokens.push(Token::Else),
                         "false" => tokens.push(Token::False),
+                        "finally" => tokens.push(Token::Finally),
                         "fun" => tokens.push(Token::Fun),
                         "for" => tokens.push(Token::For),
                         "if" => tokens.push(Token::If),
+                        "import" => tokens.push(Token::Import),
                         "nil" => tokens.push(Token::Nil),
                         "or" => tokens.push(Token::Or),
                         "print" => tokens.push(Token::Print),
                         "return" => tokens.push(Token::Return),
                         "super" => tokens.push(Token::Super),
                         "this" => tokens.push(Token::This),
+                        "throw" => tokens.push(Token::Throw),
+                        "trait" => tokens.push(Token::Trait),
                         "true" => tokens.push(Token::True),
+                        "try" => tokens.push(Token::Try),
                         "var" => tokens.push(Token::Var),
                         "while" => tokens.push(Token::While),
+                        "with" => tokens.push(Token::With),
                         _ => {
                             let string_id = string_indexer.add_string(start, end);
                             tokens.push(Token::Identifier(string_id))
@@ -168,11 +287,55 @@ impl Lexer {
                 '\n' => line_no += 1,
                 _ => return Err(format!("Unrecognised token '{}' at line: {}", ch, line_no)),
             }
+            lines.resize(tokens.len(), line);
+        }
+
+        if !interpolations.is_empty() {
+            return Err(format!(
+                "Unterminated string interpolation at line: {}",
+                line_no
+            ));
         }
 
         tokens.push(Token::Eof);
-        Ok(TokenStore::new(tokens))
+        lines.push(line_no);
+        Ok(TokenStore::new(tokens, lines))
+    }
+}
+
+/// Lexes the literal text of a string up to its closing quote or the `${` of
+/// an interpolation, returning whether an interpolation was opened. The span
+/// stored keeps the delimiters either side, starting at `start`, so that even
+/// an empty segment has one. An interpolated string becomes its segments with
+/// the tokens of each embedded expression between `InterpolationStart` and
+/// `InterpolationEnd`
+fn lex_string_segment(
+    char_iter: &mut Peekable<CharIndices>,
+    string_indexer: &mut StringIndexer,
+    tokens: &mut Vec<Token>,
+    start: usize,
+    line_no: &mut usize,
+) -> Result<bool, String> {
+    let start_line = *line_no;
+
+    while let Some((pos, ch)) = char_iter.next() {
+        match ch {
+            '"' => {
+                tokens.push(Token::String(string_indexer.add_string(start, pos)));
+                return Ok(false);
+            }
+            '$' if char_iter.peek().is_some_and(|(_, next)| *next == '{') => {
+                let (brace, _) = char_iter.next().unwrap();
+                tokens.push(Token::String(string_indexer.add_string(start, brace)));
+                tokens.push(Token::InterpolationStart);
+                return Ok(true);
+            }
+            '\n' => *line_no += 1,
+            _ => (),
+        }
     }
+
+    Err(format!("Unterminated string at line: {}", start_line))
 }
 
 #[cfg(test)]
@@ -197,6 +360,24 @@ mod test {
         assert_eq!(*token_store.next().unwrap(), Token::Eof);
     }
 
+    #[test]
+    fn detect_lines() {
+        let content = "var a;\n\"two\nlines ${a}\" b\n\n c";
+        let mut token_store = setup(content);
+
+        let mut lines = Vec::new();
+        while let Some(token) = token_store.peek() {
+            let is_eof = *token == Token::Eof;
+            lines.push(token_store.line());
+            token_store.consume();
+            if is_eof {
+                break;
+            }
+        }
+        // A string segment is on the line its text starts on
+        assert_eq!(lines, vec![1, 1, 1, 2, 2, 3, 3, 3, 3, 5, 5]);
+    }
+
     #[test]
     fn detect_parenthesis() {
         let content = "()";
@@ -217,12 +398,23 @@ mod test {
         assert_eq!(*token_store.next().unwrap(), Token::Eof);
     }
 
+    #[test]
+    fn detect_bracket() {
+        let content = "[]";
+        let mut token_store = setup(content);
+
+        assert_eq!(*token_store.next().unwrap(), Token::LeftBracket);
+        assert_eq!(*token_store.next().unwrap(), Token::RightBracket);
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+    }
+
     #[test]
     fn detect_remainig_constants() {
-        let content = ",.-+;/*";
+        let content = ",:.-+;/*";
         let mut token_store = setup(content);
 
         assert_eq!(*token_store.next().unwrap(), Token::Comma);
+        assert_eq!(*token_store.next().unwrap(), Token::Colon);
         assert_eq!(*token_store.next().unwrap(), Token::Dot);
         assert_eq!(*token_store.next().unwrap(), Token::Minus);
         assert_eq!(*token_store.next().unwrap(), Token::Plus);
@@ -232,6 +424,57 @@ mod test {
         assert_eq!(*token_store.next().unwrap(), Token::Eof);
     }
 
+    #[test]
+    fn detect_compound_assignment() {
+        let mut token_store = setup("+= -= *= /= ++ -- + - * /");
+        assert_eq!(*token_store.next().unwrap(), Token::PlusEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::MinusEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::StarEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::SlashEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::PlusPlus);
+        assert_eq!(*token_store.next().unwrap(), Token::MinusMinus);
+        assert_eq!(*token_store.next().unwrap(), Token::Plus);
+        assert_eq!(*token_store.next().unwrap(), Token::Minus);
+        assert_eq!(*token_store.next().unwrap(), Token::Star);
+        assert_eq!(*token_store.next().unwrap(), Token::Slash);
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+    }
+
+    #[test]
+    fn detect_arithmetic_operators() {
+        let mut token_store = setup("% ** ~/ * / // comment");
+        assert_eq!(*token_store.next().unwrap(), Token::Percent);
+        assert_eq!(*token_store.next().unwrap(), Token::StarStar);
+        assert_eq!(*token_store.next().unwrap(), Token::TildeSlash);
+        assert_eq!(*token_store.next().unwrap(), Token::Star);
+        assert_eq!(*token_store.next().unwrap(), Token::Slash);
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+    }
+
+    #[test]
+    fn detect_bitwise_operators() {
+        let mut token_store = setup("& | ^ ~ << >> <= >= ~/");
+        assert_eq!(*token_store.next().unwrap(), Token::Ampersand);
+        assert_eq!(*token_store.next().unwrap(), Token::Pipe);
+        assert_eq!(*token_store.next().unwrap(), Token::Caret);
+        assert_eq!(*token_store.next().unwrap(), Token::Tilde);
+        assert_eq!(*token_store.next().unwrap(), Token::LessLess);
+        assert_eq!(*token_store.next().unwrap(), Token::GreaterGreater);
+        assert_eq!(*token_store.next().unwrap(), Token::LessEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::GreaterEqual);
+        assert_eq!(*token_store.next().unwrap(), Token::TildeSlash);
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+    }
+
+    #[test]
+    fn detect_question() {
+        let mut token_store = setup("? ?? ?.");
+        assert_eq!(*token_store.next().unwrap(), Token::Question);
+        assert_eq!(*token_store.next().unwrap(), Token::QuestionQuestion);
+        assert_eq!(*token_store.next().unwrap(), Token::QuestionDot);
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+    }
+
     #[test]
     fn detect_bang() {
         let mut token_store = setup("!");
@@ -248,6 +491,9 @@ mod test {
 
         let mut token_store = setup("==");
         assert_eq!(*token_store.next().unwrap(), Token::EqualEqual);
+
+        let mut token_store = setup("=>");
+        assert_eq!(*token_store.next().unwrap(), Token::Arrow);
     }
 
     #[test]
@@ -298,12 +544,49 @@ mod test {
             "This is a string"
         }
         "#;
-        let mut string_indexer = StringIndexer::new(content);
+        let mut string_indexer = StringIndexer::new(multi_line);
         let mut token_store = setup_with_indexer(&mut string_indexer, multi_line);
         assert_eq!(*token_store.next().unwrap(), Token::LeftBrace);
         next_token_string_is(&mut token_store, &string_indexer, "\"This is a string\"");
         assert_eq!(*token_store.next().unwrap(), Token::RightBrace);
         assert_eq!(*token_store.next().unwrap(), Token::Eof);
+
+        let mut string_indexer = StringIndexer::new("\"open");
+        assert!(Lexer::new()
+            .tokenize(&mut string_indexer, "\"open")
+            .is_err());
+    }
+
+    #[test]
+    fn detect_interpolation() {
+        let content = r#""a${b + "${c}"}{}""#;
+        let mut string_indexer = StringIndexer::new(content);
+        let mut token_store = setup_with_indexer(&mut string_indexer, content);
+
+        next_token_string_is(&mut token_store, &string_indexer, "\"a${");
+        assert_eq!(*token_store.next().unwrap(), Token::InterpolationStart);
+        assert!(matches!(token_store.next(), Some(Token::Identifier(_))));
+        assert_eq!(*token_store.next().unwrap(), Token::Plus);
+        next_token_string_is(&mut token_store, &string_indexer, "\"${");
+        assert_eq!(*token_store.next().unwrap(), Token::InterpolationStart);
+        assert!(matches!(token_store.next(), Some(Token::Identifier(_))));
+        assert_eq!(*token_store.next().unwrap(), Token::InterpolationEnd);
+        next_token_string_is(&mut token_store, &string_indexer, "}\"");
+        assert_eq!(*token_store.next().unwrap(), Token::InterpolationEnd);
+        next_token_string_is(&mut token_store, &string_indexer, "}{}\"");
+        assert_eq!(*token_store.next().unwrap(), Token::Eof);
+
+        let content = r#""${ {"a": 1}["a"] }""#;
+        let mut string_indexer = StringIndexer::new(content);
+        let mut token_store = setup_with_indexer(&mut string_indexer, content);
+        next_token_string_is(&mut token_store, &string_indexer, "\"${");
+        assert_eq!(*token_store.next().unwrap(), Token::InterpolationStart);
+        assert_eq!(*token_store.next().unwrap(), Token::LeftBrace);
+
+        let mut string_indexer = StringIndexer::new("\"${a\"");
+        assert!(Lexer::new()
+            .tokenize(&mut string_indexer, "\"${a\"")
+            .is_err());
     }
 
     #[test]
@@ -312,7 +595,15 @@ mod test {
         assert_eq!(*token_store.next().unwrap(), Token::Number(123.0));
 
         let mut token_store = setup("456");
-        assert_eq!(*token_store.next().unwrap(), Token::Number(456.0));
+        assert_eq!(*token_store.next().unwrap(), Token::Integer(456));
+
+        let mut token_store = setup("9223372036854775807");
+        assert_eq!(*token_store.next().unwrap(), Token::Integer(i64::MAX));
+        let mut token_store = setup("9223372036854775808");
+        assert_eq!(
+            *token_store.next().unwrap(),
+            Token::BigInteger(BigInt::parse("9223372036854775808").unwrap())
+        );
 
         let content = "1.2.3";
         let mut lexer = Lexer::new();
@@ -327,9 +618,19 @@ mod test {
         let mut token_store = setup("and");
         assert_eq!(*token_store.next().unwrap(), Token::And);
 
+        let mut token_store = setup("break");
+        assert_eq!(*token_store.next().unwrap(), Token::Break);
+
         let mut token_store = setup("class");
         assert_eq!(*token_store.next().unwrap(), Token::Class);
 
+        let mut token_store = setup("trait with");
+        assert_eq!(*token_store.next().unwrap(), Token::Trait);
+        assert_eq!(*token_store.next().unwrap(), Token::With);
+
+        let mut token_store = setup("continue");
+        assert_eq!(*token_store.next().unwrap(), Token::Continue);
+
         let mut token_store = setup("else");
         assert_eq!(*token_store.next().unwrap(), Token::Else);
 
@@ -363,9 +664,27 @@ mod test {
         let mut token_store = setup("this");
         assert_eq!(*token_store.next().unwrap(), Token::This);
 
+        let mut token_store = setup("import");
+        assert_eq!(*token_store.next().unwrap(), Token::Import);
+
+        let mut token_store = setup("as");
+        assert_eq!(*token_store.next().unwrap(), Token::As);
+
+        let mut token_store = setup("throw");
+        assert_eq!(*token_store.next().unwrap(), Token::Throw);
+
         let mut token_store = setup("true");
         assert_eq!(*token_store.next().unwrap(), Token::True);
 
+        let mut token_store = setup("try");
+        assert_eq!(*token_store.next().unwrap(), Token::Try);
+
+        let mut token_store = setup("catch");
+        assert_eq!(*token_store.next().unwrap(), Token::Catch);
+
+        let mut token_store = setup("finally");
+        assert_eq!(*token_store.next().unwrap(), Token::Finally);
+
         let mut token_store = setup("var");
         assert_eq!(*token_store.next().unwrap(), Token::Var);
 
@@ -446,6 +765,6 @@ mod test {
         let mut lexer = Lexer::new();
         let mut string_indexer = StringIndexer::new(multi_line);
         let response = lexer.tokenize(&mut string_indexer, multi_line);
-        assert!(response.is_err_and(|value| value.contains("Unrecognised token '$' at line: 2")));
+        assert!(response.is_err_and(|value| value.contains("Unrecognised token '$' at line: 3")));
     }
 }